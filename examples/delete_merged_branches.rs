@@ -35,12 +35,13 @@ use pretty_env_logger;
 use structopt::StructOpt;
 
 use github_app::{
-    server, AppEvent, ClientPool, Event, GithubApp, JWTCredentials,
+    serve, App, AppEvent, ClientPool, Event, GithubApp, JWTCredentials,
+    LogConfig,
 };
 
 struct State {
     app_secret: Option<String>,
-    client_pool: ClientPool,
+    client_pool: Arc<ClientPool>,
 }
 
 #[derive(Clone)]
@@ -59,7 +60,11 @@ impl GithubApp for DeleteMergedBranches {
     type Future = Pin<Box<dyn Future<Output = Result<(), Self::Error>> + Send>>;
 
     fn secret(&self) -> Option<&str> {
-        self.state.app_secret.as_ref().map(String::as_str)
+        self.state.app_secret.as_deref()
+    }
+
+    fn client_pool(&self) -> Option<Arc<ClientPool>> {
+        Some(self.state.client_pool.clone())
     }
 
     fn call(&mut self, event: Event) -> Self::Future {
@@ -146,16 +151,18 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let key = nom_pem::decode_block(&key).unwrap();
 
     // Create the app.
-    let app = DeleteMergedBranches::new(Arc::new(State {
+    let state = Arc::new(State {
         app_secret: args.app_secret,
-        client_pool: ClientPool::new(
+        client_pool: Arc::new(ClientPool::new(
             args.api,
             JWTCredentials::new(args.app_id, key.data)?,
-        ),
-    }));
+        )),
+    });
+
+    let app = App::new(DeleteMergedBranches::new(state));
 
     // Run the app.
-    server(&args.addr, app).await?;
+    serve(&args.addr, app, LogConfig::default()).await?;
 
     Ok(())
 }
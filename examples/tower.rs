@@ -0,0 +1,73 @@
+// Copyright (c) 2019 Jason White
+// Copyright (c) 2019 Mike Lubinets
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Runs an app behind middleware from the tower ecosystem.
+
+use std::convert::Infallible;
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::time::Duration;
+
+use futures::{future, Future};
+use github_app::{App, Event, GithubApp, LogConfig, LoggerLayer, Overflow};
+use hyper::{server::conn::AddrStream, service::make_service_fn, Server};
+use tower::ServiceBuilder;
+
+#[derive(Clone)]
+struct MyApp;
+
+impl GithubApp for MyApp {
+    type Error = io::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<(), Self::Error>> + Send>>;
+
+    fn call(&mut self, event: Event) -> Self::Future {
+        println!("{:#?}", event);
+        Box::pin(future::ok(()))
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let addr = SocketAddr::from(([0, 0, 0, 0], 8080));
+
+    // Don't use tower's `ConcurrencyLimit` here: hyper makes a service for
+    // each connection and polls it for readiness before it reads a request,
+    // so the limit would apply to connections instead of deliveries. The
+    // app's own limit only counts deliveries and rejects any that are over it.
+    let overflow = Overflow::Reject {
+        retry_after: Duration::from_secs(5),
+    };
+    let app = App::new(MyApp).concurrency_limit(16).overflow(overflow);
+
+    let make_service = make_service_fn(move |conn: &AddrStream| {
+        let service = ServiceBuilder::new()
+            .layer(LoggerLayer::new(conn.remote_addr(), LogConfig::default()))
+            .timeout(Duration::from_secs(10))
+            .service(app.clone());
+
+        future::ok::<_, Infallible>(service)
+    });
+
+    if let Err(err) = Server::bind(&addr).serve(make_service).await {
+        println!("{}", err)
+    }
+}
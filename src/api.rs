@@ -0,0 +1,268 @@
+// Copyright (c) 2019 Jason White
+// Copyright (c) 2019 Mike Lubinets
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//! Plain requests to the GitHub REST API, for endpoints that the `hubcaps`
+//! client does not cover.
+use std::error::Error as StdError;
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+use futures::compat::Future01CompatExt;
+use reqwest::header::{self, HeaderMap};
+use reqwest::Method;
+use serde_json::Value;
+use tracing::{field, Instrument};
+
+use crate::{ClientPool, USER_AGENT};
+
+/// An error from a request to the GitHub API.
+#[derive(Debug)]
+pub enum ApiError {
+    /// The request could not be sent or the response could not be read.
+    Http(reqwest::Error),
+
+    /// GitHub responded with an error status.
+    Status { status: u16, message: String },
+}
+
+impl ApiError {
+    /// The status code of the response, if there was one.
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            ApiError::Http(err) => err.status().map(|s| s.as_u16()),
+            ApiError::Status { status, .. } => Some(*status),
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ApiError::Http(err) => write!(f, "GitHub request failed: {}", err),
+            ApiError::Status { status, message } => {
+                write!(f, "GitHub responded with {}: {}", status, message)
+            }
+        }
+    }
+}
+
+impl StdError for ApiError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            ApiError::Http(err) => Some(err),
+            ApiError::Status { .. } => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for ApiError {
+    fn from(e: reqwest::Error) -> Self {
+        ApiError::Http(e)
+    }
+}
+
+/// A successful response from the GitHub API.
+#[derive(Debug)]
+pub(crate) struct ApiResponse {
+    pub status: u16,
+
+    /// The URL of the next page, if the response is paginated.
+    pub next: Option<String>,
+
+    /// The JSON body. This is `null` if the response has no body.
+    pub body: Value,
+}
+
+impl ClientPool {
+    /// Sends a request to the GitHub API, authenticated as the app itself
+    /// (i.e., with a JWT instead of an installation token).
+    ///
+    /// The path may also be a full URL, such as the next page of a paginated
+    /// response.
+    pub(crate) async fn app_request(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<&Value>,
+    ) -> Result<ApiResponse, ApiError> {
+        let auth = format!("Bearer {}", self.creds.token());
+        self.send(method, path, &auth, body).await
+    }
+
+    /// Gets the login of the app's bot account (e.g., `my-app[bot]`). This is
+    /// the sender of events caused by the app. The login is only requested
+    /// once; concurrent callers wait for the first request instead of sending
+    /// their own.
+    pub async fn bot_login(&self) -> Result<String, ApiError> {
+        let mut bot_login = self.bot_login.lock().await;
+
+        if let Some(login) = bot_login.as_ref() {
+            return Ok(login.clone());
+        }
+
+        let response = self.app_request(Method::GET, "/app", None).await?;
+
+        let slug =
+            response.body["slug"]
+                .as_str()
+                .ok_or_else(|| ApiError::Status {
+                    status: response.status,
+                    message: "Missing app slug".into(),
+                })?;
+
+        let login = format!("{}[bot]", slug);
+        *bot_login = Some(login.clone());
+        Ok(login)
+    }
+
+    /// Sends a request to the GitHub API, authenticated as an installation
+    /// of the app.
+    pub(crate) async fn installation_request(
+        &self,
+        installation: u64,
+        method: Method,
+        path: &str,
+        body: Option<&Value>,
+    ) -> Result<ApiResponse, ApiError> {
+        let token = self.installation_token(installation).await?;
+        let auth = format!("token {}", token);
+        self.send(method, path, &auth, body).await
+    }
+
+    /// Gets an access token for an installation. Tokens are reused until
+    /// shortly before they expire.
+    async fn installation_token(
+        &self,
+        installation: u64,
+    ) -> Result<String, ApiError> {
+        if let Some((token, expires_at)) =
+            self.tokens.lock().unwrap().get(&installation)
+        {
+            if *expires_at > Utc::now() + chrono::Duration::minutes(1) {
+                return Ok(token.clone());
+            }
+        }
+
+        let path = format!("/app/installations/{}/access_tokens", installation);
+        let response = self.app_request(Method::POST, &path, None).await?;
+
+        let token = response.body["token"].as_str();
+        let expires_at = response.body["expires_at"]
+            .as_str()
+            .and_then(|s| s.parse::<DateTime<Utc>>().ok());
+
+        match (token, expires_at) {
+            (Some(token), Some(expires_at)) => {
+                self.tokens
+                    .lock()
+                    .unwrap()
+                    .insert(installation, (token.to_string(), expires_at));
+                Ok(token.to_string())
+            }
+            _ => Err(ApiError::Status {
+                status: response.status,
+                message: "Invalid installation token response".into(),
+            }),
+        }
+    }
+
+    pub(crate) async fn send(
+        &self,
+        method: Method,
+        path: &str,
+        auth: &str,
+        body: Option<&Value>,
+    ) -> Result<ApiResponse, ApiError> {
+        let url = if path.starts_with("http") {
+            path.to_string()
+        } else {
+            format!("{}{}", self.api.trim_end_matches('/'), path)
+        };
+
+        let span = tracing::debug_span!(
+            "github_request",
+            %method,
+            %url,
+            status = field::Empty,
+        );
+
+        let mut request = self
+            .client
+            .request(method, &url)
+            .header(header::AUTHORIZATION, auth)
+            .header(header::USER_AGENT, USER_AGENT)
+            .header(header::ACCEPT, "application/vnd.github.v3+json");
+
+        if let Some(body) = body {
+            request = request.json(body);
+        }
+
+        async move {
+            let mut response = request.send().compat().await?;
+
+            let status = response.status().as_u16();
+            let next = next_page(response.headers());
+
+            tracing::Span::current().record("status", &status);
+
+            // Some endpoints respond without a body (e.g., `204 No Content`).
+            let body = response
+                .json::<Value>()
+                .compat()
+                .await
+                .unwrap_or(Value::Null);
+
+            if status >= 400 {
+                let message = body["message"]
+                    .as_str()
+                    .unwrap_or("unknown error")
+                    .to_string();
+
+                tracing::debug!(%message, "GitHub API request failed");
+
+                return Err(ApiError::Status { status, message });
+            }
+
+            Ok(ApiResponse { status, next, body })
+        }
+        .instrument(span)
+        .await
+    }
+}
+
+/// Finds the URL of the next page in a `Link` header.
+fn next_page(headers: &HeaderMap) -> Option<String> {
+    let link = headers.get(header::LINK)?.to_str().ok()?;
+
+    link.split(',').find_map(|link| {
+        let mut parts = link.split(';');
+        let url = parts.next()?.trim();
+
+        if parts.any(|p| p.trim() == r#"rel="next""#) {
+            Some(
+                url.trim_start_matches('<')
+                    .trim_end_matches('>')
+                    .to_string(),
+            )
+        } else {
+            None
+        }
+    })
+}
@@ -0,0 +1,122 @@
+// Copyright (c) 2019 Jason White
+// Copyright (c) 2019 Mike Lubinets
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Redelivers the webhook deliveries that failed during a period of time.
+
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use chrono::Utc;
+use structopt::StructOpt;
+
+use github_app::{ClientPool, JWTCredentials, Redelivery};
+
+#[derive(StructOpt)]
+#[structopt(name = "github-app-redeliver")]
+struct Args {
+    /// The GitHub app ID.
+    #[structopt(long = "id")]
+    app_id: u64,
+
+    /// Path to the private key of the app (in PEM format).
+    #[structopt(long = "key", parse(from_os_str))]
+    key: PathBuf,
+
+    #[structopt(long = "api", default_value = "https://api.github.com")]
+    api: String,
+
+    /// Redelivers deliveries that failed within this long ago (e.g., `6h`).
+    #[structopt(
+        long = "since",
+        parse(try_from_str = "humantime::parse_duration")
+    )]
+    since: Duration,
+
+    /// Ignores deliveries that failed within this long ago.
+    #[structopt(
+        long = "until",
+        parse(try_from_str = "humantime::parse_duration")
+    )]
+    until: Option<Duration>,
+
+    /// Only lists the failed deliveries.
+    #[structopt(long = "dry-run")]
+    dry_run: bool,
+
+    /// How long to wait between redelivery requests.
+    #[structopt(
+        long = "interval",
+        default_value = "500ms",
+        parse(try_from_str = "humantime::parse_duration")
+    )]
+    interval: Duration,
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::from_args();
+
+    let key = fs::read(&args.key)?;
+    let key = nom_pem::decode_block(&key)
+        .map_err(|err| format!("Invalid private key: {:?}", err))?;
+
+    let pool =
+        ClientPool::new(args.api, JWTCredentials::new(args.app_id, key.data)?);
+
+    let now = Utc::now();
+    let mut redelivery =
+        Redelivery::new(now - chrono::Duration::from_std(args.since)?)
+            .dry_run(args.dry_run)
+            .interval(args.interval);
+
+    if let Some(until) = args.until {
+        redelivery = redelivery.until(now - chrono::Duration::from_std(until)?);
+    }
+
+    let mut runtime = tokio::runtime::Builder::new()
+        .basic_scheduler()
+        .enable_all()
+        .build()?;
+
+    let failed = runtime.block_on(redelivery.run(&pool))?;
+
+    for delivery in &failed {
+        println!(
+            "{} {} {}{} ({})",
+            delivery.delivered_at,
+            delivery.guid,
+            delivery.event,
+            delivery
+                .action
+                .as_ref()
+                .map_or(String::new(), |a| format!(".{}", a)),
+            delivery.status,
+        );
+    }
+
+    if args.dry_run {
+        eprintln!("{} failed deliveries", failed.len());
+    } else {
+        eprintln!("Requested redelivery of {} deliveries", failed.len());
+    }
+
+    Ok(())
+}
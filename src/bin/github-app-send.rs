@@ -0,0 +1,131 @@
+// Copyright (c) 2019 Jason White
+// Copyright (c) 2019 Mike Lubinets
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Sends a signed webhook delivery to a running app, just like GitHub would.
+
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use hyper::{Body, Client, Request, Uri};
+use structopt::StructOpt;
+
+use github_app::{fixtures, testing::TestRequest, EventType};
+
+#[derive(StructOpt)]
+#[structopt(name = "github-app-send")]
+struct Args {
+    /// The type of event to send (e.g., `pull_request`).
+    event: String,
+
+    /// Path to the JSON payload to send. Required unless `--fixture` is
+    /// given.
+    #[structopt(parse(from_os_str))]
+    payload: Option<PathBuf>,
+
+    /// Sends one of the bundled payloads for the event type instead of a file
+    /// (e.g., `opened`). Use `--list` to see the available fixtures.
+    #[structopt(long = "fixture", conflicts_with = "payload")]
+    fixture: Option<String>,
+
+    /// Lists the bundled payloads for the event type and exits.
+    #[structopt(long = "list")]
+    list: bool,
+
+    /// The secret of the app. The payload is signed with an empty secret if
+    /// not specified.
+    #[structopt(long = "secret")]
+    secret: Option<String>,
+
+    /// The URL of the app's webhook endpoint.
+    #[structopt(long = "url", default_value = "http://localhost:8080/")]
+    url: Uri,
+
+    /// The delivery ID. By default, a random one is generated.
+    #[structopt(long = "id")]
+    id: Option<String>,
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::from_args();
+
+    let event = EventType::from_str(&args.event)
+        .map_err(|_| format!("Unknown event type: {}", args.event))?;
+
+    if args.list {
+        for fixture in fixtures::for_event(event) {
+            println!("{}", fixture.name);
+        }
+
+        return Ok(());
+    }
+
+    let mut request = match (&args.payload, &args.fixture) {
+        (Some(path), _) => TestRequest::from_file(event, path)?,
+        (None, Some(name)) => fixtures::get(event, name)
+            .ok_or_else(|| format!("No fixture named '{}'", name))?
+            .request(),
+        (None, None) => {
+            return Err("Either a payload or --fixture is required".into())
+        }
+    };
+
+    if let Some(secret) = args.secret {
+        request = request.secret(secret);
+    }
+
+    if let Some(id) = args.id {
+        request = request.id(id);
+    }
+
+    let delivery_id = request.delivery_id().to_string();
+
+    let (mut parts, body) = request.into_request().into_parts();
+    parts.uri = args.url;
+    let request = Request::from_parts(parts, body);
+
+    let mut runtime = tokio::runtime::Builder::new()
+        .basic_scheduler()
+        .enable_all()
+        .build()?;
+
+    runtime.block_on(send(delivery_id, request))
+}
+
+async fn send(
+    delivery_id: String,
+    request: Request<Body>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let response = Client::new().request(request).await?;
+    let status = response.status();
+    let body = hyper::body::to_bytes(response.into_body()).await?;
+
+    eprintln!("{} {}", delivery_id, status);
+
+    if !body.is_empty() {
+        println!("{}", String::from_utf8_lossy(&body));
+    }
+
+    if status.is_success() {
+        Ok(())
+    } else {
+        Err(format!("The app responded with {}", status).into())
+    }
+}
@@ -0,0 +1,472 @@
+// Copyright (c) 2019 Jason White
+// Copyright (c) 2019 Mike Lubinets
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//! Creating and updating check runs.
+use std::collections::HashMap;
+use std::error::Error as StdError;
+use std::sync::Arc;
+
+use chrono::Utc;
+use futures::future::BoxFuture;
+use futures::{Future, FutureExt};
+use reqwest::Method;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::{
+    ApiError, ClientPool, Delivery, Event, Scope, ScopedStore, StateStore,
+};
+
+/// The maximum number of annotations GitHub accepts per request.
+const MAX_ANNOTATIONS: usize = 50;
+
+/// The status of a check run that has not concluded yet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    Queued,
+    InProgress,
+}
+
+/// The final result of a check run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Conclusion {
+    Success,
+    Failure,
+    Neutral,
+    Cancelled,
+    Skipped,
+    TimedOut,
+    ActionRequired,
+}
+
+/// The output shown on the page of a check run. The summary and text may use
+/// Markdown.
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckOutput {
+    pub title: String,
+    pub summary: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+}
+
+impl CheckOutput {
+    pub fn new<T, S>(title: T, summary: S) -> Self
+    where
+        T: Into<String>,
+        S: Into<String>,
+    {
+        CheckOutput {
+            title: title.into(),
+            summary: summary.into(),
+            text: None,
+        }
+    }
+
+    pub fn text<S>(mut self, text: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.text = Some(text.into());
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnnotationLevel {
+    Notice,
+    Warning,
+    Failure,
+}
+
+/// A comment on a range of lines in a file.
+#[derive(Debug, Clone, Serialize)]
+pub struct Annotation {
+    pub path: String,
+    pub start_line: u64,
+    pub end_line: u64,
+    #[serde(rename = "annotation_level")]
+    pub level: AnnotationLevel,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+}
+
+impl Annotation {
+    pub fn new<P, M>(
+        path: P,
+        line: u64,
+        level: AnnotationLevel,
+        message: M,
+    ) -> Self
+    where
+        P: Into<String>,
+        M: Into<String>,
+    {
+        Annotation {
+            path: path.into(),
+            start_line: line,
+            end_line: line,
+            level,
+            message: message.into(),
+            title: None,
+        }
+    }
+}
+
+/// Creates a check run. Obtained with [`ClientPool::check_run`].
+pub struct CheckRunBuilder<'a> {
+    pool: &'a ClientPool,
+    installation: u64,
+    repository: String,
+    body: Value,
+}
+
+impl ClientPool {
+    /// Starts building a check run with the given name for a commit.
+    pub fn check_run(
+        &self,
+        installation: u64,
+        repository: &str,
+        name: &str,
+        head_sha: &str,
+    ) -> CheckRunBuilder {
+        CheckRunBuilder {
+            pool: self,
+            installation,
+            repository: repository.to_string(),
+            body: json!({
+                "name": name,
+                "head_sha": head_sha,
+                "status": CheckStatus::InProgress,
+                "started_at": Utc::now(),
+            }),
+        }
+    }
+}
+
+impl<'a> CheckRunBuilder<'a> {
+    /// Sets the initial status. Defaults to in progress.
+    pub fn status(mut self, status: CheckStatus) -> Self {
+        self.body["status"] = json!(status);
+        self
+    }
+
+    /// Sets the URL of the app's own page for the check run.
+    pub fn details_url(mut self, url: &str) -> Self {
+        self.body["details_url"] = json!(url);
+        self
+    }
+
+    /// Sets an ID that the app can use to find the check run later.
+    pub fn external_id(mut self, id: &str) -> Self {
+        self.body["external_id"] = json!(id);
+        self
+    }
+
+    /// Sets the initial output.
+    pub fn output(mut self, output: CheckOutput) -> Self {
+        self.body["output"] = json!(output);
+        self
+    }
+
+    /// Creates the check run.
+    pub async fn create(self) -> Result<CheckRun<'a>, ApiError> {
+        let path = format!("/repos/{}/check-runs", self.repository);
+
+        let response = self
+            .pool
+            .installation_request(
+                self.installation,
+                Method::POST,
+                &path,
+                Some(&self.body),
+            )
+            .await?;
+
+        let id =
+            response.body["id"]
+                .as_u64()
+                .ok_or_else(|| ApiError::Status {
+                    status: response.status,
+                    message: "Missing check run ID".into(),
+                })?;
+
+        Ok(CheckRun {
+            pool: self.pool,
+            installation: self.installation,
+            repository: self.repository,
+            id,
+        })
+    }
+}
+
+/// A check run that was created with a [`CheckRunBuilder`].
+pub struct CheckRun<'a> {
+    pool: &'a ClientPool,
+    installation: u64,
+    repository: String,
+    id: u64,
+}
+
+impl<'a> CheckRun<'a> {
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Updates the status and, optionally, the output of the check run.
+    pub async fn update(
+        &self,
+        status: CheckStatus,
+        output: Option<&CheckOutput>,
+    ) -> Result<(), ApiError> {
+        let mut body = json!({ "status": status });
+
+        if let Some(output) = output {
+            body["output"] = json!(output);
+        }
+
+        self.patch(&body).await
+    }
+
+    /// Adds annotations to the check run. GitHub only accepts 50 annotations
+    /// per request, so more than that are sent in batches. Each batch must
+    /// repeat the output.
+    pub async fn annotate(
+        &self,
+        output: &CheckOutput,
+        annotations: &[Annotation],
+    ) -> Result<(), ApiError> {
+        for batch in annotations.chunks(MAX_ANNOTATIONS) {
+            let mut output = json!(output);
+            output["annotations"] = json!(batch);
+
+            self.patch(&json!({ "output": output })).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Concludes the check run.
+    pub async fn conclude(
+        self,
+        conclusion: Conclusion,
+        output: Option<&CheckOutput>,
+    ) -> Result<(), ApiError> {
+        let mut body = json!({
+            "status": "completed",
+            "conclusion": conclusion,
+            "completed_at": Utc::now(),
+        });
+
+        if let Some(output) = output {
+            body["output"] = json!(output);
+        }
+
+        self.patch(&body).await
+    }
+
+    async fn patch(&self, body: &Value) -> Result<(), ApiError> {
+        let path = format!("/repos/{}/check-runs/{}", self.repository, self.id);
+
+        self.pool
+            .installation_request(
+                self.installation,
+                Method::PATCH,
+                &path,
+                Some(body),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+/// What a check handler is asked to check.
+#[derive(Debug, Clone)]
+pub struct CheckContext {
+    pub delivery: Delivery,
+    pub installation: u64,
+
+    /// The full name of the repository.
+    pub repository: String,
+
+    /// The name of the check.
+    pub name: String,
+
+    /// The commit to check.
+    pub head_sha: String,
+
+    /// The state of the repository, if the checks were given a store with
+    /// [`Checks::store`].
+    pub state: Option<ScopedStore>,
+}
+
+impl CheckContext {
+    /// Starts building the check run for this check.
+    pub fn check_run<'a>(&self, pool: &'a ClientPool) -> CheckRunBuilder<'a> {
+        pool.check_run(
+            self.installation,
+            &self.repository,
+            &self.name,
+            &self.head_sha,
+        )
+    }
+}
+
+type Handler = Arc<
+    dyn Fn(
+            CheckContext,
+        )
+            -> BoxFuture<'static, Result<(), Box<dyn StdError + Send + Sync>>>
+        + Send
+        + Sync,
+>;
+
+/// Runs checks when GitHub asks for them.
+///
+/// When a check suite is requested or re-requested, every check is run. When
+/// a single check run is re-requested, only the check with the same name is
+/// run again.
+///
+/// # Example
+///
+/// ```no_run
+/// # use std::sync::Arc;
+/// # use github_app::{ClientPool, Delivery, Event};
+/// # use github_app::ApiError;
+/// # async fn example(pool: Arc<ClientPool>, delivery: Delivery, ev: Event) {
+/// use github_app::{CheckOutput, Checks, Conclusion};
+///
+/// let checks = Checks::new().check("lint", move |ctx| {
+///     let pool = pool.clone();
+///     async move {
+///         let run = ctx.check_run(&pool).create().await?;
+///         // ...
+///         let output = CheckOutput::new("Lint", "No problems found");
+///         run.conclude(Conclusion::Success, Some(&output)).await?;
+///         Ok::<_, ApiError>(())
+///     }
+/// });
+///
+/// checks.dispatch(&delivery, &ev).await.unwrap();
+/// # }
+/// ```
+#[derive(Default)]
+pub struct Checks {
+    store: Option<Arc<dyn StateStore>>,
+    handlers: HashMap<String, Handler>,
+}
+
+impl Checks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Gives handlers access to the state of the repository that is being
+    /// checked.
+    pub fn store(mut self, store: Arc<dyn StateStore>) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    /// Registers the handler for a check. The handler is expected to create a
+    /// check run with the same name.
+    pub fn check<F, Fut, E>(mut self, name: &str, handler: F) -> Self
+    where
+        F: Fn(CheckContext) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), E>> + Send + 'static,
+        E: Into<Box<dyn StdError + Send + Sync>>,
+    {
+        self.handlers.insert(
+            name.to_string(),
+            Arc::new(move |ctx| {
+                handler(ctx).map(|r| r.map_err(Into::into)).boxed()
+            }),
+        );
+        self
+    }
+
+    /// Runs the checks requested by a `check_suite` or `check_run` event.
+    /// Other events are ignored.
+    ///
+    /// All requested checks are run, even if one of them fails. Returns the
+    /// number of checks that were run, or the first error.
+    pub async fn dispatch(
+        &self,
+        delivery: &Delivery,
+        event: &Event,
+    ) -> Result<usize, Box<dyn StdError + Send + Sync>> {
+        let action = delivery.action.as_deref();
+
+        let (head_sha, names): (&str, Vec<&String>) = match (event, action) {
+            (Event::CheckSuite(e), Some("requested"))
+            | (Event::CheckSuite(e), Some("rerequested")) => {
+                (&e.check_suite.head_sha, self.handlers.keys().collect())
+            }
+            (Event::CheckRun(e), Some("rerequested")) => (
+                &e.check_run.head_sha,
+                self.handlers
+                    .keys()
+                    .filter(|name| **name == e.check_run.name)
+                    .collect(),
+            ),
+            _ => return Ok(0),
+        };
+
+        let (installation, repository) =
+            match (delivery.installation, &delivery.repository) {
+                (Some(i), Some(r)) => (i, r),
+                _ => return Ok(0),
+            };
+
+        let state = self.store.as_ref().map(|store| {
+            let scope = Scope::repository(installation, repository.clone());
+            ScopedStore::new(store.clone(), scope)
+        });
+
+        let mut result = Ok(names.len());
+
+        for name in names {
+            tracing::info!(check = %name, %head_sha, "running check");
+
+            let ctx = CheckContext {
+                delivery: delivery.clone(),
+                installation,
+                repository: repository.clone(),
+                name: name.clone(),
+                head_sha: head_sha.to_string(),
+                state: state.clone(),
+            };
+
+            if let Err(err) = (self.handlers[name])(ctx).await {
+                tracing::error!(check = %name, error = %err, "check failed");
+
+                if result.is_ok() {
+                    result = Err(err);
+                }
+            }
+        }
+
+        result
+    }
+}
@@ -0,0 +1,479 @@
+// Copyright (c) 2019 Jason White
+// Copyright (c) 2019 Mike Lubinets
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//! Slash commands in issue and pull request comments.
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use futures::future::BoxFuture;
+use futures::{Future, FutureExt};
+use reqwest::Method;
+use serde_json::json;
+
+use crate::{
+    ApiError, ClientPool, Delivery, Event, Scope, ScopedStore, StateStore,
+};
+
+/// A command in a comment, such as `/label bug`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Command {
+    /// The name of the command, without the leading slash.
+    pub name: String,
+
+    /// The arguments of the command. Quoted arguments may contain spaces.
+    pub args: Vec<String>,
+}
+
+impl Command {
+    /// Parses an argument.
+    pub fn arg<T>(&self, index: usize) -> Result<T, CommandError>
+    where
+        T: FromStr,
+    {
+        let arg = self.args.get(index).ok_or_else(|| {
+            CommandError::Usage(format!("Missing argument {}", index + 1))
+        })?;
+
+        arg.parse().map_err(|_| {
+            CommandError::Usage(format!("Invalid argument `{}`", arg))
+        })
+    }
+
+    /// Returns `true` if the flag (e.g., `--squash`) was given.
+    pub fn flag(&self, flag: &str) -> bool {
+        self.args.iter().any(|arg| arg == flag)
+    }
+}
+
+/// Parses the commands in a comment.
+///
+/// A command is a line that starts with `/name`. If the login of the bot is
+/// given, a line that mentions the bot (e.g., `@my-app[bot] label bug`) is
+/// also treated as a command. Lines inside code blocks and quotes are
+/// ignored.
+pub fn parse_commands(body: &str, bot: Option<&str>) -> Vec<Command> {
+    let mut commands = Vec::new();
+    let mut in_code_block = false;
+
+    for line in body.lines() {
+        let line = line.trim();
+
+        if line.starts_with("```") {
+            in_code_block = !in_code_block;
+            continue;
+        }
+
+        if in_code_block || line.starts_with('>') {
+            continue;
+        }
+
+        let rest = match (line.strip_prefix('/'), bot) {
+            (Some(rest), _) => rest,
+            (None, Some(bot)) => {
+                match line.strip_prefix('@').and_then(|l| l.strip_prefix(bot)) {
+                    Some(rest) if rest.starts_with(char::is_whitespace) => {
+                        rest.trim_start().trim_start_matches('/')
+                    }
+                    _ => continue,
+                }
+            }
+            (None, None) => continue,
+        };
+
+        let mut words = split_args(rest).into_iter();
+
+        if let Some(name) = words.next() {
+            commands.push(Command {
+                name,
+                args: words.collect(),
+            });
+        }
+    }
+
+    commands
+}
+
+/// Splits a line into words. Double quotes group words together.
+fn split_args(line: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut arg = String::new();
+    let mut quoted = false;
+    let mut in_arg = false;
+
+    for c in line.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                in_arg = true;
+            }
+            c if c.is_whitespace() && !quoted => {
+                if in_arg {
+                    args.push(std::mem::take(&mut arg));
+                    in_arg = false;
+                }
+            }
+            c => {
+                arg.push(c);
+                in_arg = true;
+            }
+        }
+    }
+
+    if in_arg {
+        args.push(arg);
+    }
+
+    args
+}
+
+/// The permission of a user on a repository, from lowest to highest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Permission {
+    None,
+    Read,
+    Triage,
+    Write,
+    Maintain,
+    Admin,
+}
+
+impl FromStr for Permission {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "none" => Permission::None,
+            "read" => Permission::Read,
+            "triage" => Permission::Triage,
+            "write" => Permission::Write,
+            "maintain" => Permission::Maintain,
+            "admin" => Permission::Admin,
+            _ => return Err(()),
+        })
+    }
+}
+
+impl ClientPool {
+    /// Gets the permission of a user on a repository.
+    pub async fn permission(
+        &self,
+        installation: u64,
+        repository: &str,
+        user: &str,
+    ) -> Result<Permission, ApiError> {
+        let path =
+            format!("/repos/{}/collaborators/{}/permission", repository, user);
+
+        let response = self
+            .installation_request(installation, Method::GET, &path, None)
+            .await?;
+
+        // `role_name` distinguishes triage and maintain, which `permission`
+        // reports as read and write. Custom roles have their own names, so
+        // those fall back to the base permission.
+        let parse = |field: &str| -> Option<Permission> {
+            response.body[field].as_str()?.parse().ok()
+        };
+        Ok(parse("role_name")
+            .or_else(|| parse("permission"))
+            .unwrap_or(Permission::None))
+    }
+}
+
+/// A failed command.
+#[derive(Debug, Clone)]
+pub enum CommandError {
+    /// The command was not used correctly. The usage of the command is
+    /// included in the reply.
+    Usage(String),
+
+    /// The command was used correctly, but it failed.
+    Failed(String),
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CommandError::Usage(message) => write!(f, "{}", message),
+            CommandError::Failed(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl From<ApiError> for CommandError {
+    fn from(e: ApiError) -> Self {
+        CommandError::Failed(e.to_string())
+    }
+}
+
+/// Everything a command handler knows about the command it is running.
+#[derive(Debug, Clone)]
+pub struct CommandContext {
+    pub command: Command,
+    pub delivery: Delivery,
+    pub installation: u64,
+
+    /// The full name of the repository.
+    pub repository: String,
+
+    /// The number of the issue or pull request.
+    pub number: u64,
+
+    /// The login of the user who wrote the comment.
+    pub sender: String,
+    pub sender_permission: Permission,
+
+    /// The state of the issue or pull request, if the commands were given a
+    /// store with [`Commands::store`].
+    pub state: Option<ScopedStore>,
+}
+
+type Handler = Arc<
+    dyn Fn(
+            CommandContext,
+        ) -> BoxFuture<'static, Result<Option<String>, CommandError>>
+        + Send
+        + Sync,
+>;
+
+struct Registration {
+    permission: Permission,
+    usage: String,
+    handler: Handler,
+}
+
+/// Dispatches the commands in issue and pull request comments to handlers.
+///
+/// For each command in a newly created comment, the permission of the
+/// commenter is checked before running the handler. The comment gets a
+/// reaction depending on the outcome, and errors (or the message returned by
+/// the handler) are posted as a reply. Unknown commands are ignored, since
+/// they may be meant for another bot.
+///
+/// # Example
+///
+/// ```no_run
+/// # use github_app::{ClientPool, Delivery, Event};
+/// # async fn example(pool: &ClientPool, delivery: &Delivery, event: &Event) {
+/// use github_app::{Commands, Permission};
+///
+/// let commands = Commands::new().bot("my-app[bot]").command(
+///     "label",
+///     Permission::Triage,
+///     "/label <name>",
+///     |ctx| async move {
+///         let label: String = ctx.command.arg(0)?;
+///         // ...
+///         Ok(Some(format!("Added `{}`", label)))
+///     },
+/// );
+///
+/// commands.dispatch(pool, delivery, event).await.unwrap();
+/// # }
+/// ```
+#[derive(Default)]
+pub struct Commands {
+    bot: Option<String>,
+    store: Option<Arc<dyn StateStore>>,
+    handlers: HashMap<String, Registration>,
+}
+
+impl Commands {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the login of the bot so that commands can also be given by
+    /// mentioning it.
+    pub fn bot<S>(mut self, login: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.bot = Some(login.into());
+        self
+    }
+
+    /// Gives handlers access to the state of the issue or pull request the
+    /// command was given on.
+    pub fn store(mut self, store: Arc<dyn StateStore>) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    /// Registers a handler for a command. Only users with at least the given
+    /// permission on the repository may run it.
+    ///
+    /// The handler may return a message to reply with.
+    pub fn command<F, Fut>(
+        mut self,
+        name: &str,
+        permission: Permission,
+        usage: &str,
+        handler: F,
+    ) -> Self
+    where
+        F: Fn(CommandContext) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Option<String>, CommandError>>
+            + Send
+            + 'static,
+    {
+        self.handlers.insert(
+            name.to_string(),
+            Registration {
+                permission,
+                usage: usage.to_string(),
+                handler: Arc::new(move |ctx| handler(ctx).boxed()),
+            },
+        );
+        self
+    }
+
+    /// Runs the commands in the comment of an `issue_comment` or
+    /// `pull_request_review_comment` event. Other events are ignored.
+    ///
+    /// Returns the number of commands that were run. Errors from commands are
+    /// reported to the commenter instead of being returned.
+    pub async fn dispatch(
+        &self,
+        pool: &ClientPool,
+        delivery: &Delivery,
+        event: &Event,
+    ) -> Result<usize, ApiError> {
+        let (body, comment_id, sender, reactions) = match event {
+            Event::IssueComment(e) => (
+                &e.comment.body,
+                e.comment.id,
+                &e.comment.user.login,
+                "issues",
+            ),
+            Event::PullRequestReviewComment(e) => (
+                &e.comment.body,
+                e.comment.id,
+                &e.comment.user.login,
+                "pulls",
+            ),
+            _ => return Ok(0),
+        };
+
+        // Edits and deletions would run the commands again.
+        if delivery.action.as_deref() != Some("created") {
+            return Ok(0);
+        }
+
+        let (installation, repository, number) = match (
+            delivery.installation,
+            &delivery.repository,
+            delivery.number,
+        ) {
+            (Some(i), Some(r), Some(n)) => (i, r.clone(), n),
+            _ => return Ok(0),
+        };
+
+        let commands: Vec<_> = parse_commands(body, self.bot.as_deref())
+            .into_iter()
+            .filter(|c| self.handlers.contains_key(&c.name))
+            .collect();
+
+        if commands.is_empty() {
+            return Ok(0);
+        }
+
+        let permission =
+            pool.permission(installation, &repository, sender).await?;
+
+        let state = self.store.as_ref().map(|store| {
+            let scope = Scope::issue(installation, repository.clone(), number);
+            ScopedStore::new(store.clone(), scope)
+        });
+
+        let mut ran = 0;
+
+        for command in commands {
+            let registration = &self.handlers[&command.name];
+
+            let result = if permission < registration.permission {
+                Err(CommandError::Failed(format!(
+                    "@{} does not have permission to run `/{}`",
+                    sender, command.name
+                )))
+            } else {
+                ran += 1;
+
+                tracing::info!(
+                    command = %command.name,
+                    %sender,
+                    "running command"
+                );
+
+                (registration.handler)(CommandContext {
+                    command: command.clone(),
+                    delivery: delivery.clone(),
+                    installation,
+                    repository: repository.clone(),
+                    number,
+                    sender: sender.clone(),
+                    sender_permission: permission,
+                    state: state.clone(),
+                })
+                .await
+            };
+
+            let (reaction, reply) = match result {
+                Ok(reply) => ("+1", reply),
+                Err(CommandError::Usage(message)) => (
+                    "confused",
+                    Some(format!(
+                        "{}\n\nUsage: `{}`",
+                        message, registration.usage
+                    )),
+                ),
+                Err(CommandError::Failed(message)) => ("-1", Some(message)),
+            };
+
+            let path = format!(
+                "/repos/{}/{}/comments/{}/reactions",
+                repository, reactions, comment_id
+            );
+            pool.installation_request(
+                installation,
+                Method::POST,
+                &path,
+                Some(&json!({ "content": reaction })),
+            )
+            .await?;
+
+            if let Some(reply) = reply {
+                let path =
+                    format!("/repos/{}/issues/{}/comments", repository, number);
+                pool.installation_request(
+                    installation,
+                    Method::POST,
+                    &path,
+                    Some(&json!({ "body": reply })),
+                )
+                .await?;
+            }
+        }
+
+        Ok(ran)
+    }
+}
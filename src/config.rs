@@ -0,0 +1,315 @@
+// Copyright (c) 2019 Jason White
+// Copyright (c) 2019 Mike Lubinets
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//! Loading of per-repository configuration files.
+use std::collections::HashMap;
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use reqwest::Method;
+use serde::de::DeserializeOwned;
+
+use crate::{ApiError, ClientPool, Delivery, EventType};
+
+/// An error from loading a configuration file.
+#[derive(Debug)]
+pub enum ConfigError {
+    /// The file could not be fetched.
+    Api(ApiError),
+
+    /// The file is not valid YAML or does not match the expected type.
+    Invalid {
+        repository: String,
+        error: serde_yaml::Error,
+    },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Api(err) => write!(f, "{}", err),
+            ConfigError::Invalid { repository, error } => {
+                write!(f, "Invalid configuration in {}: {}", repository, error)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl From<ApiError> for ConfigError {
+    fn from(e: ApiError) -> Self {
+        ConfigError::Api(e)
+    }
+}
+
+/// A cached configuration file.
+struct Entry<T> {
+    /// The repository the file was found in. This is the organization-wide
+    /// `.github` repository if the fallback was used.
+    source: String,
+
+    /// The blob SHA of the file.
+    sha: Option<String>,
+
+    value: Option<Arc<T>>,
+
+    /// When the file was last fetched.
+    fetched_at: Instant,
+}
+
+/// Loads a YAML configuration file (e.g., `.github/my-app.yml`) from the
+/// default branch of a repository and deserializes it.
+///
+/// If the repository does not have the file, it is looked up in the
+/// `.github` repository of the owner instead, so that organizations can share
+/// a configuration between all of their repositories.
+///
+/// Files are cached by repository and blob SHA. Pass every delivery to
+/// [`ConfigLoader::observe`] so that the cache is invalidated when a push
+/// changes a file. Push payloads don't always list every changed file (they
+/// include at most 20 commits), so cached files are also fetched again once
+/// they are older than the [TTL](ConfigLoader::ttl). A file whose blob SHA
+/// hasn't changed is not parsed again.
+///
+/// # Example
+///
+/// ```no_run
+/// # use github_app::{ClientPool, Delivery};
+/// # async fn example(pool: &ClientPool, delivery: &Delivery) {
+/// use github_app::ConfigLoader;
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Config {
+///     delete_merged_branches: bool,
+/// }
+///
+/// let loader = ConfigLoader::<Config>::new("my-app.yml");
+///
+/// loader.observe(delivery);
+///
+/// if let Ok(Some(config)) = loader.load(pool, delivery).await {
+///     if config.delete_merged_branches {
+///         // ...
+///     }
+/// }
+/// # }
+/// ```
+pub struct ConfigLoader<T> {
+    path: String,
+    org_fallback: bool,
+    ttl: Duration,
+    cache: Mutex<HashMap<String, Entry<T>>>,
+    _config: PhantomData<fn() -> T>,
+}
+
+impl<T> ConfigLoader<T>
+where
+    T: DeserializeOwned,
+{
+    /// Creates a loader for the file with the given name in the `.github`
+    /// directory.
+    pub fn new(file_name: &str) -> Self {
+        ConfigLoader {
+            path: format!(".github/{}", file_name),
+            org_fallback: true,
+            ttl: Duration::from_secs(5 * 60),
+            cache: Mutex::new(HashMap::new()),
+            _config: PhantomData,
+        }
+    }
+
+    /// Enables or disables the fallback to the `.github` repository of the
+    /// owner. This is enabled by default.
+    pub fn org_fallback(mut self, enabled: bool) -> Self {
+        self.org_fallback = enabled;
+        self
+    }
+
+    /// Sets how long a cached file is used before it is fetched again. The
+    /// default is five minutes.
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Loads the configuration for the repository of a delivery. Returns
+    /// `None` if the delivery is not about a repository or if there is no
+    /// configuration file.
+    pub async fn load(
+        &self,
+        pool: &ClientPool,
+        delivery: &Delivery,
+    ) -> Result<Option<Arc<T>>, ConfigError> {
+        match (delivery.installation, &delivery.repository) {
+            (Some(installation), Some(repository)) => {
+                self.load_for(pool, installation, repository).await
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Loads the configuration for a repository, given by its full name.
+    pub async fn load_for(
+        &self,
+        pool: &ClientPool,
+        installation: u64,
+        repository: &str,
+    ) -> Result<Option<Arc<T>>, ConfigError> {
+        if let Some(entry) = self.cache.lock().unwrap().get(repository) {
+            if entry.fetched_at.elapsed() < self.ttl {
+                return Ok(entry.value.clone());
+            }
+        }
+
+        let mut source = repository.to_string();
+        let mut file = self.fetch(pool, installation, &source).await?;
+
+        if file.is_none() && self.org_fallback {
+            if let Some(owner) = repository.split('/').next() {
+                source = format!("{}/.github", owner);
+                file = self.fetch(pool, installation, &source).await?;
+            }
+        }
+
+        let (sha, value) = match file {
+            Some((sha, contents)) => {
+                let value = self.parse(&source, &sha, &contents)?;
+                (Some(sha), Some(value))
+            }
+            None => (None, None),
+        };
+
+        self.cache.lock().unwrap().insert(
+            repository.to_string(),
+            Entry {
+                source,
+                sha,
+                value: value.clone(),
+                fetched_at: Instant::now(),
+            },
+        );
+
+        Ok(value)
+    }
+
+    /// Invalidates the cached configuration of the repository of the delivery
+    /// if it is a push that changed the configuration file.
+    ///
+    /// A push that doesn't list any changed files (such as a force push)
+    /// invalidates the configuration as well, since it may have changed
+    /// anything. A change to the organization-wide file invalidates the
+    /// configuration of every repository that used it.
+    pub fn observe(&self, delivery: &Delivery) {
+        if delivery.event != EventType::Push
+            || !(delivery.files.is_empty()
+                || delivery.files.iter().any(|f| *f == self.path))
+        {
+            return;
+        }
+
+        if let Some(repository) = &delivery.repository {
+            self.invalidate(repository);
+        }
+    }
+
+    /// Removes the configuration of a repository from the cache, along with
+    /// the configuration of any repository that falls back to it.
+    pub fn invalidate(&self, repository: &str) {
+        self.cache.lock().unwrap().retain(|name, entry| {
+            name != repository && entry.source != repository
+        });
+    }
+
+    /// Parses the contents of a file, reusing the cached value if another
+    /// repository has the same blob.
+    fn parse(
+        &self,
+        source: &str,
+        sha: &str,
+        contents: &[u8],
+    ) -> Result<Arc<T>, ConfigError> {
+        let cached = self
+            .cache
+            .lock()
+            .unwrap()
+            .values()
+            .find(|entry| entry.sha.as_deref() == Some(sha))
+            .and_then(|entry| entry.value.clone());
+
+        if let Some(value) = cached {
+            return Ok(value);
+        }
+
+        serde_yaml::from_slice(contents)
+            .map(Arc::new)
+            .map_err(|error| ConfigError::Invalid {
+                repository: source.to_string(),
+                error,
+            })
+    }
+
+    /// Fetches the blob SHA and contents of the file from the default branch
+    /// of a repository.
+    async fn fetch(
+        &self,
+        pool: &ClientPool,
+        installation: u64,
+        repository: &str,
+    ) -> Result<Option<(String, Vec<u8>)>, ConfigError> {
+        let path = format!("/repos/{}/contents/{}", repository, self.path);
+
+        let response = match pool
+            .installation_request(installation, Method::GET, &path, None)
+            .await
+        {
+            Ok(response) => response,
+            Err(err) if err.status() == Some(404) => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+
+        let body = response.body;
+
+        let invalid = |message: &str| ApiError::Status {
+            status: response.status,
+            message: format!("{} in contents of {}", message, repository),
+        };
+
+        let sha = body["sha"]
+            .as_str()
+            .ok_or_else(|| invalid("Missing SHA"))?
+            .to_string();
+
+        let encoded: String = body["content"]
+            .as_str()
+            .ok_or_else(|| invalid("Missing content"))?
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect();
+
+        let contents =
+            base64::decode(&encoded).map_err(|_| invalid("Invalid base64"))?;
+
+        Ok(Some((sha, contents)))
+    }
+}
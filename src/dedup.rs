@@ -0,0 +1,165 @@
+// Copyright (c) 2019 Jason White
+// Copyright (c) 2019 Mike Lubinets
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+use std::collections::{HashSet, VecDeque};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+
+use futures::{future, Future, FutureExt};
+
+use crate::{ClientPool, Delivery, Event, GithubApp, Layer};
+
+#[derive(Debug, Default)]
+struct Seen {
+    ids: HashSet<String>,
+    order: VecDeque<String>,
+}
+
+/// A layer that drops deliveries whose ID has been seen recently.
+///
+/// GitHub may deliver the same event more than once, for example when it
+/// did not get a response in time. A delivery that did not succeed (because
+/// it failed, panicked or timed out) is forgotten, so that a redelivery is
+/// still handled.
+#[derive(Debug, Clone)]
+pub struct DedupLayer {
+    capacity: usize,
+    seen: Arc<Mutex<Seen>>,
+}
+
+impl DedupLayer {
+    /// Remembers up to `capacity` delivery IDs.
+    pub fn new(capacity: usize) -> Self {
+        DedupLayer {
+            capacity,
+            seen: Arc::new(Mutex::new(Seen::default())),
+        }
+    }
+}
+
+impl<T> Layer<T> for DedupLayer
+where
+    T: GithubApp + Send + 'static,
+    T::Error: Send,
+{
+    type App = Dedup<T>;
+
+    fn layer(&self, app: T) -> Self::App {
+        Dedup {
+            app,
+            layer: self.clone(),
+        }
+    }
+}
+
+/// An app with deduplication. See [`DedupLayer`].
+#[derive(Clone)]
+pub struct Dedup<T> {
+    app: T,
+    layer: DedupLayer,
+}
+
+impl<T> GithubApp for Dedup<T>
+where
+    T: GithubApp + Send + 'static,
+    T::Error: Send,
+{
+    type Error = T::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<(), Self::Error>> + Send>>;
+
+    fn secret(&self) -> Option<&str> {
+        self.app.secret()
+    }
+
+    fn client_pool(&self) -> Option<Arc<ClientPool>> {
+        self.app.client_pool()
+    }
+
+    fn call(&mut self, payload: Event) -> Self::Future {
+        Box::pin(self.app.call(payload))
+    }
+
+    fn handle(&mut self, delivery: Delivery, payload: Event) -> Self::Future {
+        let id = match delivery.id.clone() {
+            Some(id) => id,
+            None => return Box::pin(self.app.handle(delivery, payload)),
+        };
+
+        {
+            let mut seen = self.layer.seen.lock().unwrap();
+
+            if seen.ids.contains(&id) {
+                tracing::debug!("ignoring duplicate delivery");
+                return Box::pin(future::ok(()));
+            }
+
+            seen.ids.insert(id.clone());
+            seen.order.push_back(id.clone());
+
+            while seen.order.len() > self.layer.capacity {
+                if let Some(old) = seen.order.pop_front() {
+                    seen.ids.remove(&old);
+                }
+            }
+        }
+
+        // The guard is created before the app is called and then lives in
+        // the future, so the ID is also forgotten if the app panics or the
+        // future is dropped before it finishes.
+        let mut forget = Forget {
+            id: Some(id),
+            seen: self.layer.seen.clone(),
+        };
+        let future = self.app.handle(delivery, payload);
+
+        Box::pin(future.map(move |result| {
+            if result.is_ok() {
+                forget.id = None;
+            }
+
+            result
+        }))
+    }
+}
+
+/// Forgets a delivery ID when dropped, unless the ID has been taken out.
+struct Forget {
+    id: Option<String>,
+    seen: Arc<Mutex<Seen>>,
+}
+
+impl Drop for Forget {
+    fn drop(&mut self) {
+        let id = match self.id.take() {
+            Some(id) => id,
+            None => return,
+        };
+
+        // This may run while unwinding from a panic, so a poisoned lock must
+        // not cause another one.
+        let mut seen = match self.seen.lock() {
+            Ok(seen) => seen,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+
+        seen.ids.remove(&id);
+        seen.order.retain(|i| *i != id);
+    }
+}
@@ -0,0 +1,196 @@
+// Copyright (c) 2019 Jason White
+// Copyright (c) 2019 Mike Lubinets
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+use serde_json::Value;
+use tracing::Span;
+
+use crate::{EventType, TraceContext};
+
+/// Metadata about a single webhook delivery.
+///
+/// This is extracted from the request headers and the raw JSON payload so that
+/// it is available regardless of the event type.
+///
+/// More fields may be added in the future, so this can't be constructed
+/// outside of this crate.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct Delivery {
+    /// The unique ID of the delivery, taken from the `X-GitHub-Delivery`
+    /// header.
+    pub id: Option<String>,
+
+    /// The type of event that was delivered.
+    pub event: EventType,
+
+    /// The action that triggered the event (e.g., `opened`), if any.
+    pub action: Option<String>,
+
+    /// The ID of the installation the event was sent to, if any.
+    pub installation: Option<u64>,
+
+    /// The full name of the repository (e.g., `octocat/hello-world`), if any.
+    pub repository: Option<String>,
+
+    /// The number of the issue or pull request the event is about, if any.
+    pub number: Option<u64>,
+
+    /// The login of the user whose action triggered the event, if any.
+    pub sender: Option<String>,
+
+    /// Whether the sender is a bot account.
+    pub sender_is_bot: bool,
+
+    /// The branch the event is about: the pushed branch for `push` events and
+    /// the base branch for pull request events.
+    pub branch: Option<String>,
+
+    /// The names of the labels of the issue or pull request.
+    pub labels: Vec<String>,
+
+    /// Whether the pull request is a draft, for pull request events.
+    pub draft: Option<bool>,
+
+    /// For `push` events, the paths of the files that were added, modified or
+    /// removed by the pushed commits.
+    pub files: Vec<String>,
+}
+
+impl Delivery {
+    /// Extracts the delivery metadata from a raw JSON payload.
+    ///
+    /// Fields that are missing from the payload (or if the payload is not valid
+    /// JSON) are simply left empty.
+    pub(crate) fn new(
+        id: Option<String>,
+        event: EventType,
+        payload: &[u8],
+    ) -> Self {
+        let value: Value =
+            serde_json::from_slice(payload).unwrap_or(Value::Null);
+
+        Delivery {
+            id,
+            event,
+            action: value["action"].as_str().map(String::from),
+            installation: value["installation"]["id"].as_u64(),
+            repository: value["repository"]["full_name"]
+                .as_str()
+                .map(String::from),
+            number: value["issue"]["number"]
+                .as_u64()
+                .or_else(|| value["pull_request"]["number"].as_u64())
+                .or_else(|| value["number"].as_u64()),
+            sender: value["sender"]["login"].as_str().map(String::from),
+            sender_is_bot: value["sender"]["type"] == "Bot",
+            branch: branch(&value),
+            labels: labels(&value),
+            draft: value["pull_request"]["draft"].as_bool(),
+            files: changed_files(&value),
+        }
+    }
+
+    /// Returns a trace context derived from the delivery ID, if there is one.
+    ///
+    /// This can be passed to `ClientPool::get_with_trace_context` to propagate
+    /// the trace to GitHub API requests.
+    pub fn trace_context(&self) -> Option<TraceContext> {
+        self.id
+            .as_ref()
+            .and_then(|id| TraceContext::from_delivery_id(id))
+    }
+
+    /// Records the delivery metadata in the fields of the given span.
+    pub(crate) fn record(&self, span: &Span) {
+        span.record("event", &tracing::field::display(&self.event));
+
+        if let Some(id) = &self.id {
+            span.record("id", &id.as_str());
+        }
+
+        if let Some(action) = &self.action {
+            span.record("action", &action.as_str());
+        }
+
+        if let Some(installation) = self.installation {
+            span.record("installation", &installation);
+        }
+
+        if let Some(repository) = &self.repository {
+            span.record("repository", &repository.as_str());
+        }
+
+        if let Some(context) = self.trace_context() {
+            let trace_id = format!("{:032x}", context.trace_id());
+            span.record("trace_id", &trace_id.as_str());
+        }
+    }
+}
+
+/// Finds the branch an event is about.
+fn branch(payload: &Value) -> Option<String> {
+    let git_ref = payload["ref"].as_str();
+
+    // `create` and `delete` events only have the name of the branch or tag.
+    let branch = match payload["ref_type"].as_str() {
+        Some("branch") => git_ref,
+        Some(_) => None,
+        None => git_ref.and_then(|r| r.strip_prefix("refs/heads/")),
+    };
+
+    branch
+        .or_else(|| payload["pull_request"]["base"]["ref"].as_str())
+        .map(String::from)
+}
+
+/// Collects the names of the labels of an issue or pull request.
+fn labels(payload: &Value) -> Vec<String> {
+    let labels = match payload["issue"]["labels"].as_array() {
+        Some(labels) => labels,
+        None => match payload["pull_request"]["labels"].as_array() {
+            Some(labels) => labels,
+            None => return Vec::new(),
+        },
+    };
+
+    labels
+        .iter()
+        .filter_map(|label| label["name"].as_str().map(String::from))
+        .collect()
+}
+
+/// Collects the paths of the files changed by the commits of a push.
+fn changed_files(payload: &Value) -> Vec<String> {
+    let mut files = Vec::new();
+
+    for commit in payload["commits"].as_array().into_iter().flatten() {
+        for kind in &["added", "modified", "removed"] {
+            for file in commit[*kind].as_array().into_iter().flatten() {
+                if let Some(file) = file.as_str() {
+                    if !files.iter().any(|f| f == file) {
+                        files.push(file.to_string());
+                    }
+                }
+            }
+        }
+    }
+
+    files
+}
@@ -0,0 +1,310 @@
+// Copyright (c) 2019 Jason White
+// Copyright (c) 2019 Mike Lubinets
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//! Filters that decide which events an app gets to see.
+//!
+//! Filters are attached to an app with [`GithubAppExt::filter`]. Events that
+//! don't match are acknowledged without calling the app.
+//!
+//! [`GithubAppExt::filter`]: crate::GithubAppExt::filter
+//!
+//! Filters about a property that an event does not have let the event
+//! through. For example, [`branch`] only drops `push` and pull request events
+//! for other branches, not `issues` events.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # fn example<T: github_app::GithubApp>(app: T) {
+//! use github_app::{filter, Filter, GithubAppExt};
+//!
+//! let app = app.filter(
+//!     filter::organization("octocat")
+//!         .and(filter::branch("release/*"))
+//!         .and(filter::from_bot().not()),
+//! );
+//! # }
+//! ```
+use std::sync::Arc;
+
+use futures::future::{self, Either};
+
+use crate::{ClientPool, Delivery, Event, GithubApp, Layer};
+
+/// Decides whether an event is passed to the app.
+pub trait Filter: Send + Sync {
+    fn matches(&self, delivery: &Delivery, event: &Event) -> bool;
+
+    /// Matches events that match both filters.
+    fn and<F>(self, other: F) -> And<Self, F>
+    where
+        Self: Sized,
+        F: Filter,
+    {
+        And(self, other)
+    }
+
+    /// Matches events that match either filter.
+    fn or<F>(self, other: F) -> Or<Self, F>
+    where
+        Self: Sized,
+        F: Filter,
+    {
+        Or(self, other)
+    }
+
+    /// Matches events that don't match this filter.
+    fn not(self) -> Not<Self>
+    where
+        Self: Sized,
+    {
+        Not(self)
+    }
+}
+
+impl<F> Filter for F
+where
+    F: Fn(&Delivery, &Event) -> bool + Send + Sync,
+{
+    fn matches(&self, delivery: &Delivery, event: &Event) -> bool {
+        self(delivery, event)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct And<A, B>(A, B);
+
+impl<A, B> Filter for And<A, B>
+where
+    A: Filter,
+    B: Filter,
+{
+    fn matches(&self, delivery: &Delivery, event: &Event) -> bool {
+        self.0.matches(delivery, event) && self.1.matches(delivery, event)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Or<A, B>(A, B);
+
+impl<A, B> Filter for Or<A, B>
+where
+    A: Filter,
+    B: Filter,
+{
+    fn matches(&self, delivery: &Delivery, event: &Event) -> bool {
+        self.0.matches(delivery, event) || self.1.matches(delivery, event)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Not<A>(A);
+
+impl<A> Filter for Not<A>
+where
+    A: Filter,
+{
+    fn matches(&self, delivery: &Delivery, event: &Event) -> bool {
+        !self.0.matches(delivery, event)
+    }
+}
+
+/// Matches events for one of the given repositories (e.g.,
+/// `octocat/hello-world`).
+pub fn repositories<I, S>(names: I) -> impl Filter
+where
+    I: IntoIterator<Item = S>,
+    S: Into<String>,
+{
+    let names: Vec<String> = names.into_iter().map(Into::into).collect();
+
+    move |delivery: &Delivery, _: &Event| match &delivery.repository {
+        Some(repository) => names.iter().any(|n| n == repository),
+        None => true,
+    }
+}
+
+/// Matches events for any repository except the given ones.
+pub fn exclude_repositories<I, S>(names: I) -> impl Filter
+where
+    I: IntoIterator<Item = S>,
+    S: Into<String>,
+{
+    let names: Vec<String> = names.into_iter().map(Into::into).collect();
+
+    move |delivery: &Delivery, _: &Event| match &delivery.repository {
+        Some(repository) => !names.iter().any(|n| n == repository),
+        None => true,
+    }
+}
+
+/// Matches events for repositories owned by the given user or organization.
+pub fn organization<S>(name: S) -> impl Filter
+where
+    S: Into<String>,
+{
+    let name = name.into();
+
+    move |delivery: &Delivery, _: &Event| match &delivery.repository {
+        Some(repository) => repository.split('/').next() == Some(name.as_str()),
+        None => true,
+    }
+}
+
+/// Matches events for branches that match a glob pattern. In the pattern,
+/// `*` matches anything but `/` and `**` matches anything.
+pub fn branch<S>(pattern: S) -> impl Filter
+where
+    S: Into<String>,
+{
+    let pattern = pattern.into();
+
+    move |delivery: &Delivery, _: &Event| match &delivery.branch {
+        Some(branch) => glob_match(pattern.as_bytes(), branch.as_bytes()),
+        None => true,
+    }
+}
+
+/// Matches events for issues and pull requests that have the given label.
+pub fn label<S>(name: S) -> impl Filter
+where
+    S: Into<String>,
+{
+    let name = name.into();
+
+    move |delivery: &Delivery, _: &Event| {
+        delivery.number.is_none() || delivery.labels.contains(&name)
+    }
+}
+
+/// Matches events caused by bot accounts.
+pub fn from_bot() -> impl Filter {
+    |delivery: &Delivery, _: &Event| delivery.sender_is_bot
+}
+
+/// Matches events for pull requests that are (or, with `false`, are not)
+/// drafts.
+pub fn draft(draft: bool) -> impl Filter {
+    move |delivery: &Delivery, _: &Event| {
+        delivery.draft.map_or(true, |d| d == draft)
+    }
+}
+
+fn glob_match(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        [b'*', b'*', rest @ ..] => {
+            (0..=text.len()).any(|i| glob_match(rest, &text[i..]))
+        }
+        [b'*', rest @ ..] => (0..=text.len())
+            .take_while(|&i| i == 0 || text[i - 1] != b'/')
+            .any(|i| glob_match(rest, &text[i..])),
+        [c, rest @ ..] => {
+            text.first() == Some(c) && glob_match(rest, &text[1..])
+        }
+    }
+}
+
+/// A layer that applies a filter to an app. See [`GithubAppExt::filter`].
+///
+/// [`GithubAppExt::filter`]: crate::GithubAppExt::filter
+pub struct FilterLayer<F> {
+    filter: Arc<F>,
+}
+
+impl<F> FilterLayer<F> {
+    pub fn new(filter: F) -> Self {
+        FilterLayer {
+            filter: Arc::new(filter),
+        }
+    }
+}
+
+impl<T, F> Layer<T> for FilterLayer<F>
+where
+    T: GithubApp,
+    T::Error: Send,
+    F: Filter,
+{
+    type App = Filtered<T, F>;
+
+    fn layer(&self, app: T) -> Self::App {
+        Filtered {
+            app,
+            filter: self.filter.clone(),
+        }
+    }
+}
+
+/// An app with a filter. Created with [`GithubAppExt::filter`].
+///
+/// [`GithubAppExt::filter`]: crate::GithubAppExt::filter
+///
+/// The filter needs the metadata of the delivery, so it is only applied to
+/// events passed to [`GithubApp::handle`] (which is what [`App`] does).
+///
+/// [`App`]: crate::App
+pub struct Filtered<T, F> {
+    app: T,
+    filter: Arc<F>,
+}
+
+impl<T, F> Clone for Filtered<T, F>
+where
+    T: Clone,
+{
+    fn clone(&self) -> Self {
+        Filtered {
+            app: self.app.clone(),
+            filter: self.filter.clone(),
+        }
+    }
+}
+
+impl<T, F> GithubApp for Filtered<T, F>
+where
+    T: GithubApp,
+    T::Error: Send,
+    F: Filter,
+{
+    type Error = T::Error;
+    type Future = Either<T::Future, future::Ready<Result<(), T::Error>>>;
+
+    fn secret(&self) -> Option<&str> {
+        self.app.secret()
+    }
+
+    fn client_pool(&self) -> Option<Arc<ClientPool>> {
+        self.app.client_pool()
+    }
+
+    fn call(&mut self, payload: Event) -> Self::Future {
+        Either::Left(self.app.call(payload))
+    }
+
+    fn handle(&mut self, delivery: Delivery, payload: Event) -> Self::Future {
+        if self.filter.matches(&delivery, &payload) {
+            Either::Left(self.app.handle(delivery, payload))
+        } else {
+            tracing::debug!("event filtered out");
+            Either::Right(future::ok(()))
+        }
+    }
+}
@@ -0,0 +1,218 @@
+// Copyright (c) 2019 Jason White
+// Copyright (c) 2019 Mike Lubinets
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//! A corpus of representative webhook payloads for every supported event type.
+//!
+//! The payloads are based on the examples in GitHub's documentation and
+//! anonymized. They are meant to be used in the tests of apps, for example with
+//! [`TestRequest`](crate::testing::TestRequest):
+//!
+//! ```no_run
+//! # async fn example<T>(app: T)
+//! # where
+//! #     T: github_app::GithubApp + Sync + Send + 'static,
+//! # {
+//! use github_app::{fixtures, App, EventType};
+//!
+//! let mut app = App::new(app);
+//!
+//! let fixture = fixtures::get(EventType::PullRequest, "merged").unwrap();
+//! let response = fixture.request().send(&mut app).await.unwrap();
+//! assert_eq!(response.status(), 200);
+//! # }
+//! ```
+//!
+//! This module is only available with the `fixtures` feature.
+use crate::testing::TestRequest;
+use crate::{parse_event, Event, EventType};
+
+/// A webhook payload.
+#[derive(Debug, Clone, Copy)]
+pub struct Fixture {
+    /// The type of event.
+    pub event: EventType,
+
+    /// The name of the payload. This is usually the action of the event (e.g.,
+    /// `opened`), or the variant of the event for events without an action
+    /// (e.g., `branch` or `tag`).
+    pub name: &'static str,
+
+    /// The JSON payload.
+    pub payload: &'static str,
+}
+
+impl Fixture {
+    /// Deserializes the payload.
+    pub fn parse(&self) -> Result<Event, serde_json::Error> {
+        parse_event(self.event, self.payload.as_bytes())
+    }
+
+    /// Creates a webhook request with this payload.
+    pub fn request(&self) -> TestRequest {
+        TestRequest::new(self.event, self.payload)
+    }
+}
+
+macro_rules! fixture {
+    ($event:ident, $dir:literal, $name:literal) => {
+        Fixture {
+            event: EventType::$event,
+            name: $name,
+            payload: include_str!(concat!(
+                "../fixtures/",
+                $dir,
+                "/",
+                $name,
+                ".json"
+            )),
+        }
+    };
+}
+
+static FIXTURES: &[Fixture] = &[
+    fixture!(CheckRun, "check_run", "completed"),
+    fixture!(CheckRun, "check_run", "created"),
+    fixture!(CheckRun, "check_run", "requested_action"),
+    fixture!(CheckRun, "check_run", "rerequested"),
+    fixture!(CheckSuite, "check_suite", "completed"),
+    fixture!(CheckSuite, "check_suite", "requested"),
+    fixture!(CheckSuite, "check_suite", "rerequested"),
+    fixture!(CommitComment, "commit_comment", "created"),
+    fixture!(Create, "create", "branch"),
+    fixture!(Create, "create", "tag"),
+    fixture!(Delete, "delete", "branch"),
+    fixture!(Delete, "delete", "tag"),
+    fixture!(
+        GitHubAppAuthorization,
+        "github_app_authorization",
+        "revoked"
+    ),
+    fixture!(Gollum, "gollum", "created"),
+    fixture!(Installation, "installation", "created"),
+    fixture!(Installation, "installation", "deleted"),
+    fixture!(Installation, "installation", "new_permissions_accepted"),
+    fixture!(
+        InstallationRepositories,
+        "installation_repositories",
+        "added"
+    ),
+    fixture!(
+        InstallationRepositories,
+        "installation_repositories",
+        "removed"
+    ),
+    fixture!(
+        IntegrationInstallation,
+        "integration_installation",
+        "created"
+    ),
+    fixture!(
+        IntegrationInstallation,
+        "integration_installation",
+        "deleted"
+    ),
+    fixture!(
+        IntegrationInstallation,
+        "integration_installation",
+        "new_permissions_accepted"
+    ),
+    fixture!(
+        IntegrationInstallationRepositories,
+        "integration_installation_repositories",
+        "added"
+    ),
+    fixture!(
+        IntegrationInstallationRepositories,
+        "integration_installation_repositories",
+        "removed"
+    ),
+    fixture!(IssueComment, "issue_comment", "created"),
+    fixture!(IssueComment, "issue_comment", "created_by_bot"),
+    fixture!(IssueComment, "issue_comment", "created_on_pull_request"),
+    fixture!(IssueComment, "issue_comment", "deleted"),
+    fixture!(IssueComment, "issue_comment", "edited"),
+    fixture!(Issues, "issues", "closed"),
+    fixture!(Issues, "issues", "edited"),
+    fixture!(Issues, "issues", "labeled"),
+    fixture!(Issues, "issues", "opened"),
+    fixture!(Issues, "issues", "reopened"),
+    fixture!(Label, "label", "created"),
+    fixture!(Label, "label", "deleted"),
+    fixture!(Label, "label", "edited"),
+    fixture!(Ping, "ping", "app"),
+    fixture!(PullRequest, "pull_request", "closed"),
+    fixture!(PullRequest, "pull_request", "edited"),
+    fixture!(PullRequest, "pull_request", "labeled"),
+    fixture!(PullRequest, "pull_request", "merged"),
+    fixture!(PullRequest, "pull_request", "opened"),
+    fixture!(PullRequest, "pull_request", "opened_draft"),
+    fixture!(PullRequest, "pull_request", "reopened"),
+    fixture!(PullRequest, "pull_request", "synchronize"),
+    fixture!(PullRequestReview, "pull_request_review", "dismissed"),
+    fixture!(PullRequestReview, "pull_request_review", "submitted"),
+    fixture!(
+        PullRequestReview,
+        "pull_request_review",
+        "submitted_changes_requested"
+    ),
+    fixture!(
+        PullRequestReviewComment,
+        "pull_request_review_comment",
+        "created"
+    ),
+    fixture!(
+        PullRequestReviewComment,
+        "pull_request_review_comment",
+        "deleted"
+    ),
+    fixture!(
+        PullRequestReviewComment,
+        "pull_request_review_comment",
+        "edited"
+    ),
+    fixture!(Push, "push", "branch"),
+    fixture!(Push, "push", "config_change"),
+    fixture!(Push, "push", "deleted_branch"),
+    fixture!(Push, "push", "new_branch"),
+    fixture!(Push, "push", "tag"),
+    fixture!(Repository, "repository", "archived"),
+    fixture!(Repository, "repository", "created"),
+    fixture!(Repository, "repository", "deleted"),
+    fixture!(Repository, "repository", "privatized"),
+    fixture!(Repository, "repository", "publicized"),
+    fixture!(Repository, "repository", "renamed"),
+    fixture!(Repository, "repository", "unarchived"),
+    fixture!(Watch, "watch", "started"),
+];
+
+/// Returns all payloads in the corpus.
+pub fn all() -> &'static [Fixture] {
+    FIXTURES
+}
+
+/// Returns the payload with the given event type and name, if it exists.
+pub fn get(event: EventType, name: &str) -> Option<&'static Fixture> {
+    FIXTURES.iter().find(|f| f.event == event && f.name == name)
+}
+
+/// Returns all payloads for the given type of event.
+pub fn for_event(event: EventType) -> impl Iterator<Item = &'static Fixture> {
+    FIXTURES.iter().filter(move |f| f.event == event)
+}
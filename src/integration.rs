@@ -0,0 +1,38 @@
+// Copyright (c) 2019 Jason White
+// Copyright (c) 2019 Mike Lubinets
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//! Integration with other web frameworks, for apps that serve more than just
+//! webhooks (e.g., a dashboard or an OAuth callback).
+//!
+//! Each integration can either extract a verified and parsed delivery for the
+//! framework's own handlers, or pass deliveries to an [`App`] so that all of
+//! its features (timeouts, limits, filters, etc.) apply.
+//!
+//! There is no integration for axum. axum requires Hyper 0.14 and Tokio 1,
+//! while this crate is built on Hyper 0.13 and Tokio 0.2, so the two can't
+//! share a service or a runtime.
+//!
+//! [`App`]: crate::App
+
+#[cfg(feature = "actix-web")]
+pub mod actix;
+
+#[cfg(feature = "warp")]
+pub mod warp;
@@ -0,0 +1,185 @@
+// Copyright (c) 2019 Jason White
+// Copyright (c) 2019 Mike Lubinets
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//! Integration with [actix-web](https://docs.rs/actix-web).
+//!
+//! # Example
+//!
+//! ```no_run
+//! # async fn example<T>(app: T) -> std::io::Result<()>
+//! # where
+//! #     T: github_app::GithubApp + Sync + Send + 'static,
+//! # {
+//! use actix_web::{web, HttpServer};
+//! use github_app::{integration::actix::handler, App};
+//!
+//! let app = App::new(app);
+//!
+//! HttpServer::new(move || {
+//!     actix_web::App::new()
+//!         .route("/webhook", web::post().to(handler(app.clone())))
+//!         .route("/health", web::get().to(|| async { "OK" }))
+//! })
+//! .bind("127.0.0.1:3000")?
+//! .run()
+//! .await
+//! # }
+//! ```
+use ::actix_web::{
+    dev::Payload, error::PayloadError, http::StatusCode, web, FromRequest,
+    HttpRequest, HttpResponse,
+};
+use futures::future::{poll_fn, LocalBoxFuture};
+use futures::{Stream, StreamExt};
+use hyper::{
+    header::{HeaderName, HeaderValue},
+    service::Service,
+    Body, Request,
+};
+
+use crate::{parse_webhook, App, Delivery, Event, GithubApp};
+
+/// A delivery, extracted from a request after verifying its signature.
+///
+/// The secret is taken from the [`WebhookConfig`] of the app, if there is
+/// one. Invalid requests are rejected with `400 Bad Request`.
+pub struct WebhookEvent {
+    pub delivery: Delivery,
+    pub event: Event,
+}
+
+/// Configuration for the [`WebhookEvent`] extractor. Add it to the actix app
+/// with `app_data`.
+#[derive(Debug, Clone, Default)]
+pub struct WebhookConfig {
+    secret: Option<String>,
+}
+
+impl WebhookConfig {
+    pub fn secret<S>(mut self, secret: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.secret = Some(secret.into());
+        self
+    }
+}
+
+impl FromRequest for WebhookEvent {
+    type Error = ::actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+    type Config = WebhookConfig;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let req = req.clone();
+        let payload = payload.take();
+
+        Box::pin(async move {
+            let body = read_body(payload).await?;
+
+            let secret = req
+                .app_data::<WebhookConfig>()
+                .and_then(|config| config.secret.clone());
+
+            let (delivery, event) = parse_webhook(
+                |name| req.headers().get(name).map(|v| v.as_bytes()),
+                &body,
+                secret.as_deref(),
+            )
+            .map_err(|err| {
+                ::actix_web::error::ErrorBadRequest(err.to_string())
+            })?;
+
+            Ok(WebhookEvent { delivery, event })
+        })
+    }
+}
+
+/// Creates a handler that passes requests to the app and replies with its
+/// response.
+pub fn handler<T>(
+    app: App<T>,
+) -> impl Fn(
+    HttpRequest,
+    web::Payload,
+) -> LocalBoxFuture<'static, Result<HttpResponse, ::actix_web::Error>>
+       + Clone
+       + 'static
+where
+    T: GithubApp + Sync + Send + 'static,
+{
+    move |req: HttpRequest, payload: web::Payload| {
+        let mut app = app.clone();
+
+        Box::pin(async move {
+            let body = read_body(payload).await?;
+
+            let mut request = Request::new(Body::from(body));
+
+            for (name, value) in req.headers().iter() {
+                if let (Ok(name), Ok(value)) = (
+                    HeaderName::from_bytes(name.as_str().as_bytes()),
+                    HeaderValue::from_bytes(value.as_bytes()),
+                ) {
+                    request.headers_mut().append(name, value);
+                }
+            }
+
+            let response = async {
+                poll_fn(|cx| app.poll_ready(cx)).await?;
+                app.call(request).await
+            };
+
+            let response = response.await.map_err(|err| {
+                ::actix_web::error::ErrorInternalServerError(err.to_string())
+            })?;
+
+            let status = StatusCode::from_u16(response.status().as_u16())
+                .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+
+            let mut builder = HttpResponse::build(status);
+
+            for (name, value) in response.headers() {
+                if let Ok(value) = value.to_str() {
+                    builder.header(name.as_str(), value);
+                }
+            }
+
+            let body = hyper::body::to_bytes(response.into_body())
+                .await
+                .map_err(::actix_web::error::ErrorInternalServerError)?;
+
+            Ok(builder.body(body))
+        })
+    }
+}
+
+async fn read_body<S>(mut payload: S) -> Result<Vec<u8>, PayloadError>
+where
+    S: Stream<Item = Result<web::Bytes, PayloadError>> + Unpin,
+{
+    let mut body = Vec::new();
+
+    while let Some(chunk) = payload.next().await {
+        body.extend_from_slice(&chunk?);
+    }
+
+    Ok(body)
+}
@@ -0,0 +1,115 @@
+// Copyright (c) 2019 Jason White
+// Copyright (c) 2019 Mike Lubinets
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//! Integration with [warp](https://docs.rs/warp).
+//!
+//! # Example
+//!
+//! ```no_run
+//! # async fn example<T>(app: T)
+//! # where
+//! #     T: github_app::GithubApp + Sync + Send + 'static,
+//! # {
+//! use github_app::{integration::warp::webhook, App};
+//! use warp::Filter;
+//!
+//! let routes = warp::path("webhook")
+//!     .and(webhook(App::new(app)))
+//!     .or(warp::path("health").map(|| "OK"));
+//!
+//! warp::serve(routes).run(([127, 0, 0, 1], 3000)).await;
+//! # }
+//! ```
+use ::warp::{
+    http::HeaderMap, hyper::body::Bytes, reject, Filter, Rejection, Reply,
+};
+use futures::future::{self, poll_fn};
+use hyper::{header::HeaderValue, service::Service, Body, Request, Response};
+
+use crate::{
+    error_response, parse_webhook, App, Delivery, Error, Event, GithubApp,
+};
+
+/// A webhook request that could not be handled.
+#[derive(Debug)]
+pub struct WebhookRejection(pub Error);
+
+impl reject::Reject for WebhookRejection {}
+
+/// Extracts a delivery from a `POST` request after verifying its signature.
+/// Invalid requests are rejected with a [`WebhookRejection`].
+pub fn event(
+    secret: Option<String>,
+) -> impl Filter<Extract = (Delivery, Event), Error = Rejection> + Clone {
+    ::warp::post()
+        .and(::warp::header::headers_cloned())
+        .and(::warp::body::bytes())
+        .and_then(move |headers: HeaderMap, body: Bytes| {
+            let result = parse_webhook(
+                |name| headers.get(name).map(HeaderValue::as_bytes),
+                &body,
+                secret.as_deref(),
+            );
+
+            future::ready(
+                result.map_err(|err| reject::custom(WebhookRejection(err))),
+            )
+        })
+        .untuple_one()
+}
+
+/// Passes `POST` requests to the app and replies with its response.
+pub fn webhook<T>(
+    app: App<T>,
+) -> impl Filter<Extract = (Response<Body>,), Error = Rejection> + Clone
+where
+    T: GithubApp + Sync + Send + 'static,
+{
+    ::warp::post()
+        .and(::warp::header::headers_cloned())
+        .and(::warp::body::bytes())
+        .and_then(move |headers: HeaderMap, body: Bytes| {
+            let mut app = app.clone();
+
+            async move {
+                let mut request = Request::new(Body::from(body));
+                *request.headers_mut() = headers;
+
+                let response = async {
+                    poll_fn(|cx| app.poll_ready(cx)).await?;
+                    app.call(request).await
+                };
+
+                response.await.map_err(|err| {
+                    reject::custom(WebhookRejection(Error::Http(err)))
+                })
+            }
+        })
+}
+
+/// Turns a [`WebhookRejection`] into the same response that [`App`] would
+/// have sent. Use this with `Filter::recover`.
+pub async fn recover(rejection: Rejection) -> Result<impl Reply, Rejection> {
+    match rejection.find::<WebhookRejection>() {
+        Some(WebhookRejection(err)) => error_response(err, false)
+            .map_err(|err| reject::custom(WebhookRejection(Error::Http(err)))),
+        None => Err(rejection),
+    }
+}
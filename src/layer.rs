@@ -0,0 +1,153 @@
+// Copyright (c) 2019 Jason White
+// Copyright (c) 2019 Mike Lubinets
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+use crate::filter::{Filter, FilterLayer, Filtered};
+use crate::GithubApp;
+
+/// Wraps an app in another app.
+///
+/// The outer app sees every delivery (along with its metadata) before the
+/// inner app does, and the result of the inner app after it. This is how
+/// cross-cutting concerns such as [filtering], [deduplication], [timeouts]
+/// and [logging] are implemented. Apps can add their own by implementing
+/// [`GithubApp`] for a wrapper type.
+///
+/// [filtering]: crate::filter
+/// [deduplication]: crate::DedupLayer
+/// [timeouts]: crate::TimeoutLayer
+/// [logging]: crate::DeliveryLoggerLayer
+pub trait Layer<T> {
+    /// The wrapped app.
+    type App;
+
+    fn layer(&self, app: T) -> Self::App;
+}
+
+/// A layer that leaves the app as it is.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Identity;
+
+impl<T> Layer<T> for Identity {
+    type App = T;
+
+    fn layer(&self, app: T) -> Self::App {
+        app
+    }
+}
+
+/// Two layers, one wrapped around the other.
+#[derive(Debug, Clone)]
+pub struct Stack<Inner, Outer> {
+    inner: Inner,
+    outer: Outer,
+}
+
+impl<T, Inner, Outer> Layer<T> for Stack<Inner, Outer>
+where
+    Inner: Layer<T>,
+    Outer: Layer<Inner::App>,
+{
+    type App = Outer::App;
+
+    fn layer(&self, app: T) -> Self::App {
+        self.outer.layer(self.inner.layer(app))
+    }
+}
+
+/// Builds a stack of layers.
+///
+/// Layers that are added first are the outermost ones, so they see each
+/// delivery first.
+///
+/// # Example
+///
+/// ```no_run
+/// # fn example<T>(app: T)
+/// # where
+/// #     T: github_app::GithubApp + Send + 'static,
+/// #     T::Error: Send,
+/// # {
+/// use github_app::{filter, DedupLayer, FilterLayer, Layers};
+///
+/// let app = Layers::new()
+///     .layer(DedupLayer::new(1000))
+///     .layer(FilterLayer::new(filter::organization("octocat")))
+///     .apply(app);
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Layers<L> {
+    layer: L,
+}
+
+impl Layers<Identity> {
+    pub fn new() -> Self {
+        Layers { layer: Identity }
+    }
+}
+
+impl Default for Layers<Identity> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<L> Layers<L> {
+    /// Adds a layer inside of the existing ones.
+    pub fn layer<N>(self, layer: N) -> Layers<Stack<N, L>> {
+        Layers {
+            layer: Stack {
+                inner: layer,
+                outer: self.layer,
+            },
+        }
+    }
+
+    /// Wraps the app in the layers.
+    pub fn apply<T>(&self, app: T) -> L::App
+    where
+        L: Layer<T>,
+    {
+        self.layer.layer(app)
+    }
+}
+
+/// Extension methods for apps.
+pub trait GithubAppExt: GithubApp + Sized {
+    /// Wraps the app in a layer.
+    fn layer<L>(self, layer: L) -> L::App
+    where
+        L: Layer<Self>,
+    {
+        layer.layer(self)
+    }
+
+    /// Only passes events that match the filter to the app. Other events are
+    /// acknowledged with `200 OK`.
+    fn filter<F>(self, filter: F) -> Filtered<Self, F>
+    where
+        F: Filter,
+        Self::Error: Send,
+    {
+        self.layer(FilterLayer::new(filter))
+    }
+}
+
+impl<T> GithubAppExt for T where T: GithubApp {}
@@ -18,35 +18,106 @@
 // LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
+mod api;
+mod checks;
+mod command;
+mod config;
+mod dedup;
+mod delivery;
+mod layer;
+mod limit;
 mod logger;
+mod metrics;
+mod ordering;
+mod own_events;
+mod redeliver;
+mod response;
+mod sticky;
+mod store;
+mod timeout;
+mod trace;
+mod webhook;
 
-pub use logger::Logger;
+pub mod filter;
+pub mod integration;
+pub mod record;
+pub mod source;
+
+#[cfg(feature = "testing")]
+pub mod testing;
+
+#[cfg(feature = "fixtures")]
+pub mod fixtures;
+
+pub use api::ApiError;
+pub use checks::{
+    Annotation, AnnotationLevel, CheckContext, CheckOutput, CheckRun,
+    CheckRunBuilder, CheckStatus, Checks, Conclusion,
+};
+pub use command::{
+    parse_commands, Command, CommandContext, CommandError, Commands, Permission,
+};
+pub use config::{ConfigError, ConfigLoader};
+pub use dedup::{Dedup, DedupLayer};
+pub use delivery::Delivery;
+pub use filter::{Filter, FilterLayer, Filtered};
+pub use layer::{GithubAppExt, Identity, Layer, Layers, Stack};
+pub use limit::Overflow;
+pub use logger::{
+    DeliveryLogger, DeliveryLoggerLayer, LogConfig, LogFields, LogFormat,
+    Logger, LoggerLayer,
+};
+pub use metrics::Metrics;
+pub use ordering::OrderBy;
+pub use own_events::{OwnEvents, OwnEventsLayer};
+pub use record::{Recorder, Recording};
+pub use redeliver::{HookDelivery, Redelivery};
+pub use response::IntoResponse;
+pub use source::{
+    HttpSource, Incoming, JsonLines, Source, Spool, StreamSource,
+};
+pub use sticky::StickyComment;
+pub use store::{
+    FileStore, MemoryStore, Scope, ScopedStore, StateStore, StateStoreExt,
+};
+pub use timeout::{Timeout, TimeoutError, TimeoutLayer};
+pub use trace::TraceContext;
+pub use webhook::{Webhook, WebhookLayer};
 
 pub use github_types as types;
 
 pub use types::{AppEvent, Event, EventType};
 
-use std::collections::HashMap;
+use std::any::Any;
+use std::collections::{HashMap, VecDeque};
 use std::convert::{From, Infallible};
+use std::error::Error as StdError;
 use std::fmt;
 use std::net::SocketAddr;
+use std::panic::AssertUnwindSafe;
 use std::str::{from_utf8, FromStr};
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
+use chrono::{DateTime, Utc};
 use crypto_mac::MacError;
 use derive_more::{Display, From};
-use futures::{future, Future, StreamExt};
+use futures::{future, Future, FutureExt, StreamExt};
 use hmac::{Hmac, Mac};
 use hubcaps::{Credentials, InstallationTokenGenerator};
 use hyper::{self, server::conn::AddrStream, service::make_service_fn, Server};
 use hyper::{
-    header::{self, HeaderValue},
+    header::{HeaderMap, HeaderValue},
     http::StatusCode,
     service::Service,
     Body, Request, Response,
 };
+use limit::{Limits, Overloaded};
+use ordering::Sequencer;
 use reqwest::r#async::Client;
 use sha1::Sha1;
+use tokio::sync::Semaphore;
+use tracing::{field, Instrument};
 
 // Re-export these to avoid forcing users to add a dependency on hubcaps.
 use futures::task::{Context, Poll};
@@ -55,7 +126,7 @@ use std::pin::Pin;
 
 /// A trait that a Github app must implement.
 pub trait GithubApp: Clone {
-    type Error: fmt::Display;
+    type Error: IntoResponse;
     type Future: Future<Output = Result<(), Self::Error>> + Send;
 
     /// The secret that was created when the app was created. This is used to
@@ -67,18 +138,174 @@ pub trait GithubApp: Clone {
         None
     }
 
+    /// The client pool of the app, if it has one.
+    ///
+    /// This is used to learn the login of the app's bot account, so that
+    /// events caused by the app itself can be ignored (see
+    /// [`App::allow_own_events`]). If this returns `None` (the default) and no
+    /// login is set with [`App::bot_login`], all events are passed to the app.
+    fn client_pool(&self) -> Option<Arc<ClientPool>> {
+        None
+    }
+
     /// Called when an event is received.
     fn call(&mut self, payload: Event) -> Self::Future;
+
+    /// Called when an event is received, along with metadata about its
+    /// delivery. By default, this just calls [`GithubApp::call`].
+    ///
+    /// Override this instead of `call` when the app needs the delivery ID,
+    /// installation or repository without matching on every event type, as
+    /// the helpers that take a [`Delivery`] (such as [`ConfigLoader`]) do.
+    fn handle(&mut self, _delivery: Delivery, payload: Event) -> Self::Future {
+        self.call(payload)
+    }
 }
 
+/// The error message of a failed delivery. This is attached to the extensions
+/// of the response so that it can be logged.
+pub(crate) struct ErrorMessage(pub String);
+
 /// Wraps an app in a Hyper service which can be used to run the server.
+#[derive(Clone)]
 pub struct App<T> {
-    app: T,
+    app: OwnEvents<T>,
+    options: Arc<Options>,
+}
+
+#[derive(Clone, Default)]
+struct Options {
+    hide_errors: bool,
+    metrics: Arc<Metrics>,
+    timeouts: TimeoutLayer,
+    limits: Limits,
+    order_by: Option<OrderBy>,
+    sequencer: Arc<Sequencer>,
 }
 
 impl<T> App<T> {
     pub fn new(app: T) -> Self {
-        App { app }
+        let metrics = Arc::new(Metrics::default());
+
+        App {
+            app: OwnEvents {
+                app,
+                layer: OwnEventsLayer::default(),
+            },
+            options: Arc::new(Options {
+                timeouts: TimeoutLayer::default().metrics(metrics.clone()),
+                metrics,
+                ..Options::default()
+            }),
+        }
+    }
+
+    /// If enabled, the body of the response for a failed delivery only
+    /// contains the reason phrase of the status code instead of the error
+    /// message. GitHub shows the response in the delivery log of the app, which
+    /// may not be the place for internal details.
+    ///
+    /// Errors are still logged in full. This does not apply to requests that
+    /// are rejected because they are not valid webhook payloads.
+    pub fn hide_errors(mut self, hide: bool) -> Self {
+        Arc::make_mut(&mut self.options).hide_errors = hide;
+        self
+    }
+
+    /// Sets the maximum amount of time the app may take to handle a delivery.
+    /// If the app takes any longer, its future is dropped (cancelling whatever
+    /// it was doing) and GitHub gets a `504 Gateway Timeout` response.
+    ///
+    /// By default, there is no timeout.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        Arc::make_mut(&mut self.options)
+            .timeouts
+            .set_timeout(timeout);
+        self
+    }
+
+    /// Sets the timeout for a specific type of event. This takes precedence
+    /// over the timeout set with [`App::timeout`].
+    pub fn event_timeout(
+        mut self,
+        event: EventType,
+        timeout: Duration,
+    ) -> Self {
+        Arc::make_mut(&mut self.options)
+            .timeouts
+            .set_event(event, timeout);
+        self
+    }
+
+    /// Limits the number of deliveries that are handled at the same time
+    /// across all connections.
+    ///
+    /// A delivery only counts towards the limit once it is its turn to be
+    /// handled (see [`App::order_by`]). Idle keep-alive connections and
+    /// deliveries waiting behind others with the same key do not take up any
+    /// capacity.
+    pub fn concurrency_limit(mut self, limit: usize) -> Self {
+        Arc::make_mut(&mut self.options).limits.global =
+            Some(Arc::new(Semaphore::new(limit)));
+        self
+    }
+
+    /// Limits the number of deliveries that are handled at the same time for
+    /// a single installation of the app. This keeps a burst of deliveries for
+    /// one installation from exhausting its API rate limit.
+    ///
+    /// The global permit is acquired first, so a delivery that has to wait for
+    /// this limit already counts towards the global one.
+    pub fn installation_concurrency_limit(mut self, limit: usize) -> Self {
+        Arc::make_mut(&mut self.options).limits.per_installation = Some(limit);
+        self
+    }
+
+    /// Sets what happens to a delivery when a concurrency limit has been
+    /// reached. By default, deliveries are queued.
+    pub fn overflow(mut self, overflow: Overflow) -> Self {
+        Arc::make_mut(&mut self.options).limits.overflow = overflow;
+        self
+    }
+
+    /// Handles deliveries for the same repository, issue or pull request one
+    /// after another instead of concurrently. This keeps, for example, the
+    /// handler for a `closed` pull request from racing with the handler for a
+    /// `synchronize` that was delivered just before it.
+    ///
+    /// The timeout set with [`App::timeout`] does not include the time spent
+    /// waiting for previous deliveries.
+    pub fn order_by(mut self, order_by: OrderBy) -> Self {
+        Arc::make_mut(&mut self.options).order_by = Some(order_by);
+        self
+    }
+
+    /// Sets the login of the app's bot account (e.g., `my-app[bot]`).
+    ///
+    /// Events caused by the bot itself (such as a comment posted by the app)
+    /// are acknowledged without calling the app. This keeps the app from
+    /// reacting to its own actions in a loop. Usually, the login does not
+    /// need to be set, because it is requested through the app's
+    /// [`GithubApp::client_pool`] when it is first needed.
+    pub fn bot_login<S>(mut self, login: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.app.layer = std::mem::take(&mut self.app.layer).login(login);
+        self
+    }
+
+    /// Passes events of the given type to the app even if they were caused
+    /// by its own bot account.
+    pub fn allow_own_events(mut self, event: EventType) -> Self {
+        self.app.layer = std::mem::take(&mut self.app.layer).allow(event);
+        self
+    }
+
+    /// Returns the metrics of this app. These are shared with all of its
+    /// clones.
+    pub fn metrics(&self) -> Arc<Metrics> {
+        self.options.metrics.clone()
     }
 }
 
@@ -87,30 +314,172 @@ where
     T: GithubApp + Sync + Send + 'static,
 {
     async fn handle_request(
-        mut app: T,
+        app: OwnEvents<T>,
+        options: Arc<Options>,
         req: Request<Body>,
     ) -> Result<Response<Body>, hyper::http::Error> {
-        let payload = match parse_request(req, app.secret()).await {
-            Ok(p) => p,
-            Err(err) => {
-                return Response::builder()
-                    .status(StatusCode::BAD_REQUEST)
-                    .body(err.to_string().into())
+        // Everything done on behalf of this delivery (including the app's own
+        // logging and API requests) happens inside this span. The fields are
+        // filled in once the payload has been parsed.
+        let span = tracing::info_span!(
+            "delivery",
+            id = field::Empty,
+            event = field::Empty,
+            action = field::Empty,
+            installation = field::Empty,
+            repository = field::Empty,
+            trace_id = field::Empty,
+        );
+
+        if let Some(id) = req
+            .headers()
+            .get("X-GitHub-Delivery")
+            .and_then(|header| header.to_str().ok())
+        {
+            span.record("id", &id);
+        }
+
+        // Panics are caught so that GitHub still gets a response and the
+        // delivery is logged. Otherwise, the connection is simply dropped.
+        let parsed = AssertUnwindSafe(parse_request(req, app.secret()))
+            .catch_unwind()
+            .instrument(tracing::debug_span!(parent: &span, "parse_request"))
+            .await;
+
+        let (delivery, payload) = match parsed {
+            Ok(Ok(p)) => p,
+            Ok(Err(err)) => {
+                span.in_scope(
+                    || tracing::warn!(error = %err, "rejected webhook request"),
+                );
+                return error_response(&err, false);
+            }
+            Err(panic) => {
+                let panic = Panic::new(panic);
+                options.metrics.inc_panics();
+                span.in_scope(|| tracing::error!(%panic, "parsing panicked"));
+                return error_response(&panic, options.hide_errors);
+            }
+        };
+
+        delivery.record(&span);
+
+        // The timeout is a layer like any other. Since the app is only called
+        // once it is this delivery's turn (see below), the time spent waiting
+        // for other deliveries is not included.
+        let mut app = options.timeouts.layer(app);
+
+        // The call itself is inside the future so that a panic in the
+        // synchronous part of `call` is caught as well.
+        let call = AssertUnwindSafe({
+            let delivery = delivery.clone();
+            async move { app.handle(delivery, payload).await }
+        })
+        .catch_unwind()
+        .instrument(span.clone());
+
+        let installation = delivery.installation;
+        let limits = options.limits.clone();
+
+        // Permits are only acquired once it is this delivery's turn.
+        // Otherwise, deliveries waiting behind others with the same key would
+        // hold on to permits that other repositories could use.
+        let call = async move {
+            let _global = limits.acquire_global().await?;
+            let _installation =
+                limits.acquire_installation(installation).await?;
+
+            Ok::<_, Overloaded>(call.await)
+        };
+
+        let key = options
+            .order_by
+            .and_then(|order_by| order_by.key(&delivery));
+        let result = options.sequencer.run(key, call).await;
+
+        let mut response = match result {
+            Ok(Ok(Ok(()))) => Response::builder()
+                .status(StatusCode::OK)
+                .body(Body::empty())?,
+            Ok(Ok(Err(err))) => {
+                // Timeouts have already been logged by the layer.
+                if let TimeoutError::App(err) = &err {
+                    span.in_scope(
+                        || tracing::error!(error = %err, "app failed"),
+                    );
+                }
+
+                error_response(&err, options.hide_errors)?
+            }
+            Ok(Err(panic)) => {
+                let panic = Panic::new(panic);
+                options.metrics.inc_panics();
+                span.in_scope(|| tracing::error!(%panic, "app panicked"));
+                error_response(&panic, options.hide_errors)?
+            }
+            Err(overloaded) => {
+                options.metrics.inc_rejected();
+                span.in_scope(|| tracing::warn!("{}", overloaded));
+                error_response(&overloaded, false)?
             }
         };
 
-        if let Err(err) = app.call(payload).await {
-            return Response::builder()
-                .status(StatusCode::INTERNAL_SERVER_ERROR)
-                .body(err.to_string().into());
+        response.extensions_mut().insert(delivery);
+
+        Ok(response)
+    }
+}
+
+/// A panic that was caught while handling a delivery.
+struct Panic(String);
+
+impl Panic {
+    fn new(payload: Box<dyn Any + Send>) -> Self {
+        let message = match payload.downcast::<String>() {
+            Ok(message) => *message,
+            Err(payload) => match payload.downcast_ref::<&str>() {
+                Some(message) => (*message).to_string(),
+                None => String::from("Box<Any>"),
+            },
         };
 
-        Response::builder()
-            .status(StatusCode::OK)
-            .body(Body::empty())
+        Panic(message)
     }
 }
 
+impl fmt::Display for Panic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Panicked: {}", self.0)
+    }
+}
+
+impl IntoResponse for Panic {}
+
+fn error_response<E>(
+    err: &E,
+    hide: bool,
+) -> Result<Response<Body>, hyper::http::Error>
+where
+    E: IntoResponse,
+{
+    let status = err.status();
+
+    let body = if hide {
+        status.canonical_reason().unwrap_or_default().to_string()
+    } else {
+        err.body()
+    };
+
+    let mut response = Response::builder().status(status).body(body.into())?;
+
+    response.headers_mut().extend(err.headers());
+    response
+        .extensions_mut()
+        .insert(ErrorMessage(err.to_string()));
+
+    Ok(response)
+}
+
 impl<T> Service<Request<Body>> for App<T>
 where
     T: GithubApp + Sync + Send + 'static,
@@ -130,19 +499,31 @@ where
 
     fn call(&mut self, req: Request<Body>) -> Self::Future {
         let app = self.app.clone();
-        let response = Self::handle_request(app, req);
+        let options = self.options.clone();
+        let response = Self::handle_request(app, options, req);
         Box::pin(response)
     }
 }
 
-#[derive(From, Display)]
+#[derive(Debug, From, Display)]
 pub enum PayloadError {
     Hyper(hyper::Error),
     Json(serde_json::Error),
     Mac(MacError),
 }
 
-#[derive(Display)]
+impl StdError for PayloadError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            PayloadError::Hyper(e) => Some(e),
+            PayloadError::Json(e) => Some(e),
+            // `MacError` carries no details beyond the mismatch itself.
+            PayloadError::Mac(_) => None,
+        }
+    }
+}
+
+#[derive(Debug, Display)]
 pub enum Error {
     #[display(fmt = "Invalid or missing Content-Type")]
     ContentType,
@@ -165,6 +546,22 @@ pub enum Error {
     Payload(PayloadError),
 }
 
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            Error::Http(e) => Some(e),
+            Error::Payload(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl IntoResponse for Error {
+    fn status(&self) -> StatusCode {
+        StatusCode::BAD_REQUEST
+    }
+}
+
 impl From<hyper::Error> for Error {
     fn from(e: hyper::Error) -> Self {
         Error::Payload(PayloadError::Hyper(e))
@@ -196,64 +593,156 @@ impl From<hyper::http::Error> for Error {
 async fn parse_request(
     req: Request<Body>,
     secret: Option<&str>,
-) -> Result<Event, Error> {
-    if req.headers().get(header::CONTENT_TYPE)
-        != Some(&HeaderValue::from_static("application/json"))
-    {
-        return Err(Error::ContentType);
-    }
-
-    // Parse the event type.
-    let event = req
-        .headers()
-        .get("X-Github-Event")
-        .ok_or_else(|| Error::MissingEvent)
-        .and_then(move |header| {
-            from_utf8(header.as_bytes())
-                .map_err(|_| Error::InvalidEvent)
-                .and_then(|s| {
-                    EventType::from_str(s).map_err(|_| Error::InvalidEvent)
-                })
-        })?;
-
-    // Parse the signature
-    let signature = req
-        .headers()
-        .get("X-Hub-Signature")
-        .ok_or_else(|| Error::MissingSignature)
-        .and_then(move |header| {
-            from_utf8(header.as_bytes())
-                .map_err(|_| Error::InvalidSignature)
-                .and_then(|s| {
-                    Signature::from_str(s).map_err(|_| Error::InvalidSignature)
-                })
-        })?;
-
-    let mut mac =
-        secret.map(|s| Hmac::<Sha1>::new_varkey(s.as_bytes()).unwrap());
+) -> Result<(Delivery, Event), Error> {
+    let headers = WebhookHeaders::parse(|name| {
+        req.headers().get(name).map(HeaderValue::as_bytes)
+    })?;
 
     // Parse the JSON payload.
     let mut buf = Vec::new();
 
     let mut body = req.into_body();
     while let Some(chunk) = body.next().await {
-        let chunk = chunk?;
+        buf.extend(chunk?);
+    }
 
-        if let Some(mac) = mac.as_mut() {
-            mac.input(&chunk);
-        }
+    headers.parse_body(&buf, secret)
+}
+
+/// A webhook delivery that was received some other way than through [`App`],
+/// such as from a message queue, a serverless function or a log file.
+///
+/// # Example
+///
+/// ```no_run
+/// # fn example(body: Vec<u8>) -> Result<(), github_app::Error> {
+/// use github_app::WebhookRequest;
+/// use hyper::header::HeaderMap;
+///
+/// let mut headers = HeaderMap::new();
+/// headers.insert("Content-Type", "application/json".parse().unwrap());
+/// headers.insert("X-GitHub-Event", "ping".parse().unwrap());
+/// headers.insert("X-Hub-Signature", "sha1=...".parse().unwrap());
+///
+/// let request = WebhookRequest { headers, body };
+/// let (delivery, event) = request.parse(Some("secret"))?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct WebhookRequest {
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+}
 
-        buf.extend(chunk);
+impl WebhookRequest {
+    /// Verifies the signature of the delivery (if a secret is given) and
+    /// parses it, just like [`App`] does.
+    pub fn parse(
+        &self,
+        secret: Option<&str>,
+    ) -> Result<(Delivery, Event), Error> {
+        parse_webhook(
+            |name| self.headers.get(name).map(HeaderValue::as_bytes),
+            &self.body,
+            secret,
+        )
     }
+}
+
+/// Verifies the signature of a payload, given the value of the
+/// `X-Hub-Signature` header.
+pub fn verify_signature(
+    secret: &str,
+    header: &str,
+    body: &[u8],
+) -> Result<(), Error> {
+    let signature =
+        Signature::from_str(header).map_err(|_| Error::InvalidSignature)?;
+
+    signature.verify(secret, body)
+}
+
+/// Parses a webhook delivery, given a function that looks up its headers.
+/// The header names passed to `header` are case-insensitive.
+pub(crate) fn parse_webhook<'a, H>(
+    header: H,
+    body: &[u8],
+    secret: Option<&str>,
+) -> Result<(Delivery, Event), Error>
+where
+    H: Fn(&str) -> Option<&'a [u8]>,
+{
+    WebhookHeaders::parse(header)?.parse_body(body, secret)
+}
+
+/// The parts of a webhook delivery that are sent as headers.
+struct WebhookHeaders {
+    event: EventType,
+    delivery_id: Option<String>,
+    signature: Signature,
+}
+
+impl WebhookHeaders {
+    fn parse<'a, H>(header: H) -> Result<Self, Error>
+    where
+        H: Fn(&str) -> Option<&'a [u8]>,
+    {
+        if header("Content-Type") != Some(&b"application/json"[..]) {
+            return Err(Error::ContentType);
+        }
+
+        // Parse the event type.
+        let event = header("X-Github-Event")
+            .ok_or_else(|| Error::MissingEvent)
+            .and_then(move |header| {
+                from_utf8(header).map_err(|_| Error::InvalidEvent).and_then(
+                    |s| EventType::from_str(s).map_err(|_| Error::InvalidEvent),
+                )
+            })?;
+
+        let delivery_id = header("X-GitHub-Delivery")
+            .and_then(|header| from_utf8(header).ok())
+            .map(String::from);
+
+        // Parse the signature
+        let signature = header("X-Hub-Signature")
+            .ok_or_else(|| Error::MissingSignature)
+            .and_then(move |header| {
+                from_utf8(header)
+                    .map_err(|_| Error::InvalidSignature)
+                    .and_then(|s| {
+                        Signature::from_str(s)
+                            .map_err(|_| Error::InvalidSignature)
+                    })
+            })?;
 
-    if let Some(mac) = mac {
-        mac.verify(signature.digest())?;
+        Ok(WebhookHeaders {
+            event,
+            delivery_id,
+            signature,
+        })
     }
 
-    parse_event(event, &buf).map_err(Error::from)
+    /// Verifies the signature of the payload and parses it.
+    fn parse_body(
+        self,
+        body: &[u8],
+        secret: Option<&str>,
+    ) -> Result<(Delivery, Event), Error> {
+        if let Some(secret) = secret {
+            self.signature.verify(secret, body)?;
+        }
+
+        let payload = parse_event(self.event, body)?;
+
+        Ok((Delivery::new(self.delivery_id, self.event, body), payload))
+    }
 }
 
-fn parse_event(
+/// Deserializes the payload of an event of the given type. Event types that
+/// are not supported yet result in an error.
+pub fn parse_event(
     event_type: EventType,
     slice: &[u8],
 ) -> Result<Event, serde_json::Error> {
@@ -305,7 +794,12 @@ fn parse_event(
             Event::Repository(serde_json::from_slice(slice)?)
         }
         EventType::Watch => Event::Watch(serde_json::from_slice(slice)?),
-        _ => panic!(format!("Unimplemented event type: {}", event_type)),
+        _ => {
+            return Err(serde::de::Error::custom(format!(
+                "Unimplemented event type: {}",
+                event_type
+            )))
+        }
     })
 }
 
@@ -323,8 +817,23 @@ pub struct ClientPool {
     api: String,
 
     creds: JWTCredentials,
+
+    /// Installation tokens for plain API requests, along with when they
+    /// expire. hubcaps doesn't expose the tokens of its generators, so these
+    /// are requested separately. GitHub allows several tokens per
+    /// installation.
+    tokens: Mutex<HashMap<u64, (String, DateTime<Utc>)>>,
+
+    /// The login of the app's bot account, once it is known.
+    bot_login: tokio::sync::Mutex<Option<String>>,
+
+    /// HTTP clients that send a trace context, most recently used last.
+    traced: Mutex<VecDeque<(TraceContext, Client)>>,
 }
 
+/// The number of HTTP clients with a trace context that are kept around.
+const TRACED_CLIENTS: usize = 16;
+
 impl ClientPool {
     pub fn new(api: String, creds: JWTCredentials) -> Self {
         ClientPool {
@@ -332,11 +841,77 @@ impl ClientPool {
             client: Client::new(),
             api,
             creds,
+            tokens: Mutex::new(HashMap::new()),
+            bot_login: tokio::sync::Mutex::new(None),
+            traced: Mutex::new(VecDeque::new()),
         }
     }
 
     /// Gets a Github client for the given installation ID.
     pub fn get(&self, installation: u64) -> Github {
+        self.get_with_client(installation, self.client.clone())
+    }
+
+    /// Gets a Github client for the given installation ID that sends the given
+    /// trace context with every API request.
+    ///
+    /// Since the header has to be set on the HTTP client itself, this creates
+    /// a new HTTP client (with its own connections) for each trace context.
+    /// The last few are reused, so getting several clients with the same
+    /// context while handling a delivery is cheap, but each delivery pays for
+    /// new connections to GitHub. Use [`ClientPool::get`] unless the API
+    /// requests need to show up in the trace.
+    pub fn get_with_trace_context(
+        &self,
+        installation: u64,
+        context: &TraceContext,
+    ) -> Github {
+        match self.traced_client(context) {
+            Ok(client) => self.get_with_client(installation, client),
+            Err(err) => {
+                tracing::warn!(
+                    error = %err,
+                    "failed to create client with trace context"
+                );
+                self.get(installation)
+            }
+        }
+    }
+
+    /// Gets or creates an HTTP client that sends the given trace context.
+    fn traced_client(
+        &self,
+        context: &TraceContext,
+    ) -> Result<Client, reqwest::Error> {
+        let mut traced = self.traced.lock().unwrap();
+
+        if let Some(i) = traced.iter().position(|(c, _)| c == context) {
+            let entry = traced.remove(i).unwrap();
+            let client = entry.1.clone();
+            traced.push_back(entry);
+            return Ok(client);
+        }
+
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert(
+            "traceparent",
+            reqwest::header::HeaderValue::from_str(&context.traceparent())
+                .expect("traceparent is always a valid header value"),
+        );
+
+        let client = Client::builder().default_headers(headers).build()?;
+
+        if traced.len() >= TRACED_CLIENTS {
+            traced.pop_front();
+        }
+        traced.push_back((*context, client.clone()));
+
+        Ok(client)
+    }
+
+    fn get_with_client(&self, installation: u64, client: Client) -> Github {
+        tracing::debug!(installation, "creating GitHub client");
+
         let mut pool = self.pool.lock().unwrap();
 
         let token_generator = pool
@@ -353,7 +928,7 @@ impl ClientPool {
             self.api.clone(),
             USER_AGENT,
             Credentials::InstallationToken(token_generator),
-            self.client.clone(),
+            client,
         )
     }
 }
@@ -369,9 +944,29 @@ impl Signature {
         Signature { digest }
     }
 
+    /// Computes the signature of a payload the same way GitHub does.
+    pub fn compute(secret: &str, payload: &[u8]) -> Signature {
+        let mut mac = Hmac::<Sha1>::new_varkey(secret.as_bytes()).unwrap();
+        mac.input(payload);
+        Signature::new(mac.result().code().to_vec())
+    }
+
     pub fn digest(&self) -> &[u8] {
         &self.digest
     }
+
+    /// Checks that this is the signature of the payload.
+    pub fn verify(&self, secret: &str, payload: &[u8]) -> Result<(), Error> {
+        let mut mac = Hmac::<Sha1>::new_varkey(secret.as_bytes()).unwrap();
+        mac.input(payload);
+        Ok(mac.verify(&self.digest)?)
+    }
+}
+
+impl fmt::Display for Signature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "sha1={}", hex::encode(&self.digest))
+    }
 }
 
 impl FromStr for Signature {
@@ -394,20 +989,35 @@ impl FromStr for Signature {
     }
 }
 
+/// Runs the app on the given address with the default logging configuration.
 pub fn server<T>(
     addr: &SocketAddr,
     app: T,
 ) -> impl Future<Output = Result<(), hyper::Error>>
+where
+    T: GithubApp + Sync + Send + Unpin + 'static,
+{
+    serve(addr, App::new(app), LogConfig::default())
+}
+
+/// Like [`server`], but with an already wrapped app and a custom logging
+/// configuration.
+pub fn serve<T>(
+    addr: &SocketAddr,
+    app: App<T>,
+    log_config: LogConfig,
+) -> impl Future<Output = Result<(), hyper::Error>>
 where
     T: GithubApp + Sync + Send + Unpin + 'static,
 {
     // Create our service factory.
     let new_service = make_service_fn(move |socket: &AddrStream| {
         // Create our app.
-        let service = App::new(app.clone());
+        let service = app.clone();
 
         // Add logging middleware
-        let service = Logger::new(socket.remote_addr(), service);
+        let service =
+            Logger::with_config(socket.remote_addr(), service, log_config);
 
         future::ready(Ok::<_, Infallible>(service))
     });
@@ -0,0 +1,153 @@
+// Copyright (c) 2019 Jason White
+// Copyright (c) 2019 Mike Lubinets
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use hyper::{
+    header::{self, HeaderMap, HeaderValue},
+    http::StatusCode,
+};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::IntoResponse;
+
+/// What to do with a delivery when a concurrency limit has been reached.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overflow {
+    /// Wait until another delivery has finished before handling it.
+    Queue,
+
+    /// Respond immediately with `503 Service Unavailable` and a `Retry-After`
+    /// header.
+    Reject {
+        /// The value of the `Retry-After` header.
+        retry_after: Duration,
+    },
+}
+
+impl Default for Overflow {
+    fn default() -> Self {
+        Overflow::Queue
+    }
+}
+
+/// Concurrency limits shared by all clones of an app.
+#[derive(Clone, Default)]
+pub(crate) struct Limits {
+    /// Limits the number of deliveries handled at the same time.
+    pub global: Option<Arc<Semaphore>>,
+
+    /// Limits the number of deliveries handled at the same time for a single
+    /// installation.
+    pub per_installation: Option<usize>,
+
+    /// The semaphores for each installation. These are created on demand and
+    /// kept for as long as the app is running; there is only one for each
+    /// installation of the app.
+    pub installations: Arc<Mutex<HashMap<u64, Arc<Semaphore>>>>,
+
+    pub overflow: Overflow,
+}
+
+impl Limits {
+    /// Acquires a permit to handle a delivery under the global limit.
+    ///
+    /// Returns `Ok(None)` if there is no global limit.
+    pub async fn acquire_global(
+        &self,
+    ) -> Result<Option<OwnedSemaphorePermit>, Overloaded> {
+        let semaphore = match &self.global {
+            Some(semaphore) => semaphore.clone(),
+            None => return Ok(None),
+        };
+
+        acquire(semaphore, self.overflow).await
+    }
+
+    /// Acquires a permit to handle a delivery for the given installation.
+    ///
+    /// Returns `Ok(None)` if there is no limit for this delivery.
+    pub async fn acquire_installation(
+        &self,
+        installation: Option<u64>,
+    ) -> Result<Option<OwnedSemaphorePermit>, Overloaded> {
+        let (limit, installation) = match (self.per_installation, installation)
+        {
+            (Some(limit), Some(installation)) => (limit, installation),
+            _ => return Ok(None),
+        };
+
+        let semaphore = self
+            .installations
+            .lock()
+            .unwrap()
+            .entry(installation)
+            .or_insert_with(|| Arc::new(Semaphore::new(limit)))
+            .clone();
+
+        acquire(semaphore, self.overflow).await
+    }
+}
+
+async fn acquire(
+    semaphore: Arc<Semaphore>,
+    overflow: Overflow,
+) -> Result<Option<OwnedSemaphorePermit>, Overloaded> {
+    match overflow {
+        Overflow::Queue => Ok(Some(semaphore.acquire_owned().await)),
+        Overflow::Reject { retry_after } => semaphore
+            .try_acquire_owned()
+            .map(Some)
+            .map_err(|_| Overloaded { retry_after }),
+    }
+}
+
+/// A delivery was rejected because a concurrency limit has been reached.
+pub(crate) struct Overloaded {
+    pub retry_after: Duration,
+}
+
+impl fmt::Display for Overloaded {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Too many deliveries in progress")
+    }
+}
+
+impl IntoResponse for Overloaded {
+    fn status(&self) -> StatusCode {
+        StatusCode::SERVICE_UNAVAILABLE
+    }
+
+    fn headers(&self) -> HeaderMap {
+        // Retry-After only has a resolution of seconds. Round up so that the
+        // client never retries too early.
+        let mut seconds = self.retry_after.as_secs();
+        if self.retry_after.subsec_nanos() > 0 {
+            seconds += 1;
+        }
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::RETRY_AFTER, HeaderValue::from(seconds));
+        headers
+    }
+}
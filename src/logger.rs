@@ -20,30 +20,148 @@
 // SOFTWARE.
 use std::fmt;
 use std::net::SocketAddr;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use futures::task::{Context, Poll};
-use futures::Future;
+use futures::{Future, FutureExt};
 use humantime::format_duration;
-use hyper::{service::Service, Request, Response};
+use hyper::{
+    header::HeaderMap, service::Service, Method, Request, Response, Uri,
+};
 use log;
+use serde_json::{json, Map, Value};
 use std::pin::Pin;
+use std::sync::Arc;
+
+use crate::{
+    ClientPool, Delivery, ErrorMessage, Event, GithubApp, IntoResponse, Layer,
+};
+
+/// The format of the log records emitted by [`Logger`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    /// A single human-readable line per request.
+    Text,
+
+    /// A single JSON object per request.
+    Json,
+}
+
+impl Default for LogFormat {
+    fn default() -> Self {
+        LogFormat::Text
+    }
+}
+
+/// Selects which fields are included in JSON log records. All fields are
+/// enabled by default.
+///
+/// This has no effect on the text format.
+#[derive(Debug, Clone, Copy)]
+pub struct LogFields {
+    pub remote_addr: bool,
+    pub method: bool,
+    pub uri: bool,
+    pub delivery: bool,
+    pub event: bool,
+    pub action: bool,
+    pub installation: bool,
+    pub repository: bool,
+    pub status: bool,
+    pub duration: bool,
+    pub error: bool,
+}
+
+impl Default for LogFields {
+    fn default() -> Self {
+        LogFields {
+            remote_addr: true,
+            method: true,
+            uri: true,
+            delivery: true,
+            event: true,
+            action: true,
+            installation: true,
+            repository: true,
+            status: true,
+            duration: true,
+            error: true,
+        }
+    }
+}
+
+/// Configuration for [`Logger`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LogConfig {
+    pub format: LogFormat,
+    pub fields: LogFields,
+}
+
+impl LogConfig {
+    /// Returns a configuration that logs all fields as JSON.
+    pub fn json() -> Self {
+        LogConfig {
+            format: LogFormat::Json,
+            fields: LogFields::default(),
+        }
+    }
+}
 
 /// Wraps a service to provide logging on both the request and the response.
+///
+/// This logs HTTP requests, including those that are not valid deliveries.
+/// To log the deliveries an app handles, no matter where they come from, use
+/// a [`DeliveryLoggerLayer`].
 pub struct Logger<S> {
     remote_addr: SocketAddr,
+    config: LogConfig,
     service: S,
 }
 
 impl<S> Logger<S> {
     pub fn new(remote_addr: SocketAddr, service: S) -> Self {
+        Self::with_config(remote_addr, service, LogConfig::default())
+    }
+
+    pub fn with_config(
+        remote_addr: SocketAddr,
+        service: S,
+        config: LogConfig,
+    ) -> Self {
         Logger {
             remote_addr,
+            config,
             service,
         }
     }
 }
 
+/// A [tower] layer that wraps services in a [`Logger`].
+///
+/// [tower]: https://docs.rs/tower
+#[derive(Debug, Clone, Copy)]
+pub struct LoggerLayer {
+    remote_addr: SocketAddr,
+    config: LogConfig,
+}
+
+impl LoggerLayer {
+    pub fn new(remote_addr: SocketAddr, config: LogConfig) -> Self {
+        LoggerLayer {
+            remote_addr,
+            config,
+        }
+    }
+}
+
+impl<S> tower_layer::Layer<S> for LoggerLayer {
+    type Service = Logger<S>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        Logger::with_config(self.remote_addr, service, self.config)
+    }
+}
+
 impl<S, B, RB> Service<Request<B>> for Logger<S>
 where
     B: Send,
@@ -59,15 +177,21 @@ where
 
     fn poll_ready(
         &mut self,
-        _cx: &mut Context<'_>,
+        cx: &mut Context<'_>,
     ) -> Poll<Result<(), Self::Error>> {
-        Poll::Ready(Ok(()))
+        self.service.poll_ready(cx)
     }
 
     fn call(&mut self, req: Request<B>) -> Self::Future {
         let method = req.method().clone();
         let uri = req.uri().clone();
         let remote_addr = self.remote_addr;
+        let config = self.config;
+
+        // The delivery ID and event type are taken from the request so that
+        // they are available even if the payload fails to parse.
+        let delivery_id = header_str(req.headers(), "X-GitHub-Delivery");
+        let event = header_str(req.headers(), "X-GitHub-Event");
 
         let start = Instant::now();
 
@@ -76,6 +200,26 @@ where
         let response = async move {
             let response = response.await;
 
+            if config.format == LogFormat::Json {
+                let record = json_record(
+                    &config.fields,
+                    &remote_addr,
+                    &method,
+                    &uri,
+                    delivery_id,
+                    event,
+                    &response,
+                    start.elapsed(),
+                );
+
+                match &response {
+                    Ok(_) => log::info!("{}", record),
+                    Err(_) => log::error!("{}", record),
+                };
+
+                return response;
+            }
+
             match &response {
                 Ok(response) => log::info!(
                     "[{}] {} {} - {} ({})",
@@ -101,3 +245,239 @@ where
         Box::pin(response)
     }
 }
+
+/// Builds a JSON log record with the enabled fields. Fields without a value
+/// are omitted.
+#[allow(clippy::too_many_arguments)]
+fn json_record<RB, E>(
+    fields: &LogFields,
+    remote_addr: &SocketAddr,
+    method: &Method,
+    uri: &Uri,
+    delivery_id: Option<String>,
+    event: Option<String>,
+    response: &Result<Response<RB>, E>,
+    duration: Duration,
+) -> Value
+where
+    E: fmt::Display,
+{
+    let mut record = Map::new();
+
+    let mut insert = |enabled: bool, key: &str, value: Value| {
+        if enabled && !value.is_null() {
+            record.insert(key.into(), value);
+        }
+    };
+
+    // Prefer the metadata attached by the app. This includes information from
+    // the payload itself.
+    let delivery = response
+        .as_ref()
+        .ok()
+        .and_then(|r| r.extensions().get::<Delivery>());
+
+    insert(
+        fields.remote_addr,
+        "remote_addr",
+        json!(remote_addr.ip().to_string()),
+    );
+    insert(fields.method, "method", json!(method.as_str()));
+    insert(fields.uri, "uri", json!(uri.to_string()));
+    insert(
+        fields.delivery,
+        "delivery",
+        json!(delivery.and_then(|d| d.id.clone()).or(delivery_id)),
+    );
+    insert(
+        fields.event,
+        "event",
+        json!(delivery.map(|d| d.event.to_string()).or(event)),
+    );
+    insert(
+        fields.action,
+        "action",
+        json!(delivery.and_then(|d| d.action.as_ref())),
+    );
+    insert(
+        fields.installation,
+        "installation",
+        json!(delivery.and_then(|d| d.installation)),
+    );
+    insert(
+        fields.repository,
+        "repository",
+        json!(delivery.and_then(|d| d.repository.as_ref())),
+    );
+
+    match response {
+        Ok(response) => {
+            insert(fields.status, "status", json!(response.status().as_u16()));
+            insert(
+                fields.error,
+                "error",
+                json!(response
+                    .extensions()
+                    .get::<ErrorMessage>()
+                    .map(|e| &e.0)),
+            );
+        }
+        Err(err) => insert(fields.error, "error", json!(err.to_string())),
+    }
+
+    insert(
+        fields.duration,
+        "duration_ms",
+        json!(duration.as_secs_f64() * 1000.0),
+    );
+
+    Value::Object(record)
+}
+
+/// A layer that logs every delivery the app handles, along with its outcome.
+///
+/// Unlike [`Logger`], this sees the parsed delivery instead of the HTTP
+/// request. It also logs deliveries from a [`Source`](crate::Source) and
+/// can be placed anywhere in a stack of layers, for example inside of a
+/// filter to only log the deliveries that get through.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DeliveryLoggerLayer {
+    config: LogConfig,
+}
+
+impl DeliveryLoggerLayer {
+    pub fn new(config: LogConfig) -> Self {
+        DeliveryLoggerLayer { config }
+    }
+}
+
+impl<T> Layer<T> for DeliveryLoggerLayer
+where
+    T: GithubApp + Send + 'static,
+{
+    type App = DeliveryLogger<T>;
+
+    fn layer(&self, app: T) -> Self::App {
+        DeliveryLogger {
+            app,
+            config: self.config,
+        }
+    }
+}
+
+/// An app that logs its deliveries. See [`DeliveryLoggerLayer`].
+#[derive(Clone)]
+pub struct DeliveryLogger<T> {
+    app: T,
+    config: LogConfig,
+}
+
+impl<T> GithubApp for DeliveryLogger<T>
+where
+    T: GithubApp + Send + 'static,
+{
+    type Error = T::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<(), Self::Error>> + Send>>;
+
+    fn secret(&self) -> Option<&str> {
+        self.app.secret()
+    }
+
+    fn client_pool(&self) -> Option<Arc<ClientPool>> {
+        self.app.client_pool()
+    }
+
+    fn call(&mut self, payload: Event) -> Self::Future {
+        Box::pin(self.app.call(payload))
+    }
+
+    fn handle(&mut self, delivery: Delivery, payload: Event) -> Self::Future {
+        let config = self.config;
+        let start = Instant::now();
+        let future = self.app.handle(delivery.clone(), payload);
+
+        Box::pin(future.map(move |result| {
+            log_delivery(&config, &delivery, &result, start.elapsed());
+            result
+        }))
+    }
+}
+
+fn log_delivery<E>(
+    config: &LogConfig,
+    delivery: &Delivery,
+    result: &Result<(), E>,
+    duration: Duration,
+) where
+    E: IntoResponse,
+{
+    if config.format == LogFormat::Json {
+        let fields = &config.fields;
+        let mut record = Map::new();
+
+        let mut insert = |enabled: bool, key: &str, value: Value| {
+            if enabled && !value.is_null() {
+                record.insert(key.into(), value);
+            }
+        };
+
+        insert(fields.delivery, "delivery", json!(delivery.id));
+        insert(fields.event, "event", json!(delivery.event.to_string()));
+        insert(fields.action, "action", json!(delivery.action));
+        insert(
+            fields.installation,
+            "installation",
+            json!(delivery.installation),
+        );
+        insert(fields.repository, "repository", json!(delivery.repository));
+
+        let status = match result {
+            Ok(()) => 200,
+            Err(err) => err.status().as_u16(),
+        };
+        insert(fields.status, "status", json!(status));
+
+        if let Err(err) = result {
+            insert(fields.error, "error", json!(err.to_string()));
+        }
+
+        insert(
+            fields.duration,
+            "duration_ms",
+            json!(duration.as_secs_f64() * 1000.0),
+        );
+
+        match result {
+            Ok(()) => log::info!("{}", Value::Object(record)),
+            Err(_) => log::error!("{}", Value::Object(record)),
+        };
+
+        return;
+    }
+
+    let event = match &delivery.action {
+        Some(action) => format!("{}.{}", delivery.event, action),
+        None => delivery.event.to_string(),
+    };
+    let id = delivery.id.as_deref().unwrap_or("-");
+
+    match result {
+        Ok(()) => {
+            log::info!("{} {} - OK ({})", event, id, format_duration(duration))
+        }
+        Err(err) => log::error!(
+            "{} {} - {} ({})",
+            event,
+            id,
+            err,
+            format_duration(duration)
+        ),
+    };
+}
+
+fn header_str(headers: &HeaderMap, name: &str) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(String::from)
+}
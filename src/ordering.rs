@@ -0,0 +1,102 @@
+// Copyright (c) 2019 Jason White
+// Copyright (c) 2019 Mike Lubinets
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use futures::Future;
+
+use crate::Delivery;
+
+/// Determines which deliveries are handled one after another.
+///
+/// Deliveries with the same key are handled sequentially in the order they
+/// were received, while deliveries with different keys are still handled in
+/// parallel. Deliveries that do not belong to a repository are never held
+/// back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderBy {
+    /// Handle all deliveries for the same repository sequentially.
+    Repository,
+
+    /// Handle all deliveries for the same issue or pull request sequentially.
+    /// Deliveries for a repository that are not about a specific issue or pull
+    /// request (e.g., `push`) are ordered with respect to each other, but not
+    /// with respect to issues and pull requests.
+    Issue,
+}
+
+impl OrderBy {
+    /// Returns the key of the delivery, if it has one.
+    pub(crate) fn key(self, delivery: &Delivery) -> Option<String> {
+        let repository = delivery.repository.as_ref()?;
+
+        match (self, delivery.number) {
+            (OrderBy::Issue, Some(number)) => {
+                Some(format!("{}#{}", repository, number))
+            }
+            _ => Some(repository.clone()),
+        }
+    }
+}
+
+/// Runs futures with the same key sequentially.
+#[derive(Default)]
+pub(crate) struct Sequencer {
+    locks: Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>,
+}
+
+impl Sequencer {
+    /// Runs the future once all previous futures with the same key have
+    /// finished. If there is no key, the future is run immediately.
+    pub async fn run<F>(&self, key: Option<String>, future: F) -> F::Output
+    where
+        F: Future,
+    {
+        let key = match key {
+            Some(key) => key,
+            None => return future.await,
+        };
+
+        let lock = self
+            .locks
+            .lock()
+            .unwrap()
+            .entry(key.clone())
+            .or_default()
+            .clone();
+
+        // The lock is fair, so futures are run in the order they got here.
+        let output = {
+            let _guard = lock.lock().await;
+            future.await
+        };
+
+        // Clean up the lock unless someone else is waiting on it. New
+        // references can only be created while holding the map lock, so this
+        // is not racy.
+        let mut locks = self.locks.lock().unwrap();
+        if Arc::strong_count(&lock) == 2 {
+            locks.remove(&key);
+        }
+
+        output
+    }
+}
@@ -0,0 +1,155 @@
+// Copyright (c) 2019 Jason White
+// Copyright (c) 2019 Mike Lubinets
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//! Ignoring events caused by the app itself.
+use std::collections::HashSet;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use futures::Future;
+
+use crate::{ClientPool, Delivery, Event, EventType, GithubApp, Layer};
+
+/// A layer that acknowledges events caused by the app's own bot account
+/// (such as a comment posted by the app) without calling the app. This keeps
+/// the app from reacting to its own actions in a loop.
+///
+/// Unless a login is set with [`OwnEventsLayer::login`], the login of the bot
+/// account is requested through the app's [`GithubApp::client_pool`] the
+/// first time a bot sends an event. Without either, all events are passed
+/// on.
+///
+/// [`App`](crate::App) already applies this layer, so it only needs to be
+/// added when the app is driven some other way.
+#[derive(Debug, Clone, Default)]
+pub struct OwnEventsLayer {
+    login: Option<String>,
+    allowed: HashSet<EventType>,
+}
+
+impl OwnEventsLayer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the login of the app's bot account (e.g., `my-app[bot]`).
+    pub fn login<S>(mut self, login: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.login = Some(login.into());
+        self
+    }
+
+    /// Passes events of the given type to the app even if they were caused
+    /// by its own bot account.
+    pub fn allow(mut self, event: EventType) -> Self {
+        self.allowed.insert(event);
+        self
+    }
+}
+
+impl<T> Layer<T> for OwnEventsLayer
+where
+    T: GithubApp + Send + 'static,
+{
+    type App = OwnEvents<T>;
+
+    fn layer(&self, app: T) -> Self::App {
+        OwnEvents {
+            app,
+            layer: self.clone(),
+        }
+    }
+}
+
+/// An app that does not see its own events. See [`OwnEventsLayer`].
+#[derive(Clone)]
+pub struct OwnEvents<T> {
+    pub(crate) app: T,
+    pub(crate) layer: OwnEventsLayer,
+}
+
+impl<T> GithubApp for OwnEvents<T>
+where
+    T: GithubApp + Send + 'static,
+{
+    type Error = T::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<(), Self::Error>> + Send>>;
+
+    fn secret(&self) -> Option<&str> {
+        self.app.secret()
+    }
+
+    fn client_pool(&self) -> Option<Arc<ClientPool>> {
+        self.app.client_pool()
+    }
+
+    fn call(&mut self, payload: Event) -> Self::Future {
+        Box::pin(self.app.call(payload))
+    }
+
+    fn handle(&mut self, delivery: Delivery, payload: Event) -> Self::Future {
+        let sender = match &delivery.sender {
+            Some(sender)
+                if delivery.sender_is_bot
+                    && !self.layer.allowed.contains(&delivery.event) =>
+            {
+                sender.clone()
+            }
+            _ => return Box::pin(self.app.handle(delivery, payload)),
+        };
+
+        let login = self.layer.login.clone();
+        let pool = self.app.client_pool();
+        let mut app = self.app.clone();
+
+        Box::pin(async move {
+            if is_own(&sender, login, pool).await {
+                tracing::debug!("ignoring event caused by the app itself");
+                return Ok(());
+            }
+
+            app.handle(delivery, payload).await
+        })
+    }
+}
+
+/// Returns `true` if the sender is the app's bot account.
+async fn is_own(
+    sender: &str,
+    login: Option<String>,
+    pool: Option<Arc<ClientPool>>,
+) -> bool {
+    match (login, pool) {
+        (Some(login), _) => login == sender,
+        (None, Some(pool)) => match pool.bot_login().await {
+            Ok(login) => login == sender,
+            Err(err) => {
+                tracing::warn!(
+                    error = %err,
+                    "failed to get the login of the app's bot account"
+                );
+                false
+            }
+        },
+        (None, None) => false,
+    }
+}
@@ -0,0 +1,443 @@
+// Copyright (c) 2019 Jason White
+// Copyright (c) 2019 Mike Lubinets
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//! Recording of webhook deliveries so that they can be replayed later.
+//!
+//! [`Recorder`] wraps a service and appends every request it receives to a
+//! JSONL file. The deliveries in that file can then be fed through an app
+//! again with [`replay`], for example to reproduce a problem locally.
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Utc};
+use futures::future::poll_fn;
+use futures::task::{Context, Poll};
+use futures::Future;
+use hyper::{
+    header::{HeaderMap, HeaderName, HeaderValue},
+    http::StatusCode,
+    service::Service,
+    Body, Request, Response,
+};
+use serde::{Deserialize, Serialize};
+use std::pin::Pin;
+
+use crate::{App, GithubApp, Signature, WebhookRequest};
+
+/// A webhook delivery as it was received.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedDelivery {
+    /// When the delivery was received.
+    pub timestamp: DateTime<Utc>,
+
+    /// The request headers. Header names are in lowercase.
+    pub headers: BTreeMap<String, String>,
+
+    /// The payload, exactly as it was received. It is stored base64-encoded,
+    /// so its signature still matches even if it is not valid UTF-8.
+    #[serde(with = "base64_body")]
+    pub body: Vec<u8>,
+}
+
+impl RecordedDelivery {
+    /// The delivery ID, taken from the `X-GitHub-Delivery` header.
+    pub fn id(&self) -> Option<&str> {
+        self.header("x-github-delivery")
+    }
+
+    /// The event type, taken from the `X-GitHub-Event` header.
+    pub fn event(&self) -> Option<&str> {
+        self.header("x-github-event")
+    }
+
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).map(String::as_str)
+    }
+
+    /// Replaces the signature of the delivery with one computed using the
+    /// given secret. This is needed to replay a delivery through an app that
+    /// does not have the secret it was originally signed with.
+    pub fn resign(&mut self, secret: &str) {
+        let signature = Signature::compute(secret, &self.body);
+
+        self.headers
+            .insert("x-hub-signature".into(), signature.to_string());
+    }
+
+    /// Builds an HTTP request with the original headers and payload.
+    pub fn into_request(self) -> Result<Request<Body>, hyper::http::Error> {
+        let mut request = Request::post("/");
+
+        for (name, value) in &self.headers {
+            request = request.header(
+                HeaderName::from_bytes(name.as_bytes())?,
+                HeaderValue::from_str(value)?,
+            );
+        }
+
+        request.body(Body::from(self.body))
+    }
+
+    /// Converts the delivery into a [`WebhookRequest`].
+    pub fn into_webhook_request(
+        self,
+    ) -> Result<WebhookRequest, hyper::http::Error> {
+        let mut headers = HeaderMap::new();
+
+        for (name, value) in &self.headers {
+            headers.insert(
+                HeaderName::from_bytes(name.as_bytes())?,
+                HeaderValue::from_str(value)?,
+            );
+        }
+
+        Ok(WebhookRequest {
+            headers,
+            body: self.body,
+        })
+    }
+
+    /// Sends the delivery to the app and returns its response.
+    pub async fn send<T>(
+        self,
+        app: &mut App<T>,
+    ) -> Result<Response<Body>, hyper::http::Error>
+    where
+        T: GithubApp + Sync + Send + 'static,
+    {
+        let request = self.into_request()?;
+        poll_fn(|cx| app.poll_ready(cx)).await?;
+        app.call(request).await
+    }
+}
+
+/// Reads all deliveries from a recording. Empty lines are skipped.
+pub fn read<P>(path: P) -> io::Result<Vec<RecordedDelivery>>
+where
+    P: AsRef<Path>,
+{
+    let mut deliveries = Vec::new();
+
+    for line in BufReader::new(File::open(path)?).lines() {
+        let line = line?;
+
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        deliveries.push(serde_json::from_str(&line)?);
+    }
+
+    Ok(deliveries)
+}
+
+/// Sends every delivery in a recording to the app, in order, and returns the
+/// responses.
+///
+/// If a secret is given, the deliveries are signed with it before they are
+/// sent. Otherwise, they keep their original signatures.
+///
+/// # Example
+///
+/// ```no_run
+/// # async fn example<T>(app: T) -> std::io::Result<()>
+/// # where
+/// #     T: github_app::GithubApp + Sync + Send + 'static,
+/// # {
+/// use github_app::{record, App};
+///
+/// let mut app = App::new(app);
+///
+/// for response in record::replay("deliveries.jsonl", &mut app, None).await? {
+///     println!("{}", response.status());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub async fn replay<P, T>(
+    path: P,
+    app: &mut App<T>,
+    secret: Option<&str>,
+) -> io::Result<Vec<Response<Body>>>
+where
+    P: AsRef<Path>,
+    T: GithubApp + Sync + Send + 'static,
+{
+    let path = path.as_ref().to_owned();
+    let deliveries =
+        tokio::task::spawn_blocking(move || read(path))
+            .await
+            .map_err(|err| io::Error::new(io::ErrorKind::Other, err))??;
+
+    let mut responses = Vec::new();
+
+    for mut delivery in deliveries {
+        if let Some(secret) = secret {
+            delivery.resign(secret);
+        }
+
+        let response = delivery
+            .send(app)
+            .await
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+        responses.push(response);
+    }
+
+    Ok(responses)
+}
+
+/// A JSONL file that deliveries are appended to.
+///
+/// Optionally, the file is rotated once it grows past a certain size. The
+/// rotated files get a numeric suffix (e.g., `deliveries.jsonl.1`), with
+/// higher numbers being older.
+#[derive(Debug)]
+pub struct Recording {
+    path: PathBuf,
+    rotate: Option<(u64, usize)>,
+    file: Mutex<Option<(File, u64)>>,
+}
+
+impl Recording {
+    /// Creates a recording that appends to the file at the given path. The
+    /// file is created if it does not exist.
+    pub fn new<P>(path: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        Recording {
+            path: path.into(),
+            rotate: None,
+            file: Mutex::new(None),
+        }
+    }
+
+    /// Rotates the file once it is larger than `max_size` bytes, keeping at
+    /// most `keep` old files around.
+    pub fn rotate(mut self, max_size: u64, keep: usize) -> Self {
+        self.rotate = Some((max_size, keep));
+        self
+    }
+
+    /// Appends a delivery to the file.
+    pub fn append(&self, delivery: &RecordedDelivery) -> io::Result<()> {
+        let mut line = serde_json::to_vec(delivery)?;
+        line.push(b'\n');
+
+        let mut file = self.file.lock().unwrap();
+
+        if let (Some((max_size, keep)), Some((_, size))) =
+            (self.rotate, file.as_ref())
+        {
+            if *size > 0 && *size + line.len() as u64 > max_size {
+                *file = None;
+                self.rotate_files(keep)?;
+            }
+        }
+
+        if file.is_none() {
+            let f = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)?;
+            let size = f.metadata()?.len();
+            *file = Some((f, size));
+        }
+
+        let (f, size) = file.as_mut().unwrap();
+        f.write_all(&line)?;
+        *size += line.len() as u64;
+
+        Ok(())
+    }
+
+    fn rotated_path(&self, n: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{}", n));
+        path.into()
+    }
+
+    fn rotate_files(&self, keep: usize) -> io::Result<()> {
+        if keep == 0 {
+            return fs::remove_file(&self.path);
+        }
+
+        let _ = fs::remove_file(self.rotated_path(keep));
+
+        for n in (1..keep).rev() {
+            let from = self.rotated_path(n);
+            if from.exists() {
+                fs::rename(from, self.rotated_path(n + 1))?;
+            }
+        }
+
+        fs::rename(&self.path, self.rotated_path(1))
+    }
+}
+
+/// Wraps a service to record every request it receives.
+///
+/// Deliveries are recorded before they are handled, so even those that fail
+/// to parse or that crash the app end up in the recording. Failing to write
+/// to the recording is logged but does not affect the delivery.
+///
+/// # Example
+///
+/// ```no_run
+/// # async fn example<T>(app: T) -> hyper::Result<()>
+/// # where
+/// #     T: github_app::GithubApp + Sync + Send + Unpin + 'static,
+/// # {
+/// use std::convert::Infallible;
+/// use std::sync::Arc;
+///
+/// use github_app::{App, Recorder, Recording};
+/// use hyper::{service::make_service_fn, Server};
+///
+/// let app = App::new(app);
+/// let recording =
+///     Arc::new(Recording::new("deliveries.jsonl").rotate(100 << 20, 5));
+///
+/// let make_service = make_service_fn(move |_| {
+///     let service = Recorder::new(recording.clone(), app.clone());
+///     async move { Ok::<_, Infallible>(service) }
+/// });
+///
+/// Server::bind(&([127, 0, 0, 1], 3000).into())
+///     .serve(make_service)
+///     .await
+/// # }
+/// ```
+pub struct Recorder<S> {
+    recording: Arc<Recording>,
+    service: S,
+}
+
+impl<S> Recorder<S> {
+    pub fn new(recording: Arc<Recording>, service: S) -> Self {
+        Recorder { recording, service }
+    }
+}
+
+impl<S> Service<Request<Body>> for Recorder<S>
+where
+    S: Service<Request<Body>, Response = Response<Body>>
+        + Clone
+        + Send
+        + 'static,
+    S::Future: Send,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<
+        Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>,
+    >;
+
+    fn poll_ready(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        // The body has to be read before the request can be passed on, so the
+        // service that was just made ready is moved into the future and
+        // replaced with a clone.
+        let clone = self.service.clone();
+        let mut service = std::mem::replace(&mut self.service, clone);
+        let recording = self.recording.clone();
+
+        Box::pin(async move {
+            let (parts, body) = req.into_parts();
+
+            let body = match hyper::body::to_bytes(body).await {
+                Ok(body) => body,
+                Err(err) => {
+                    tracing::warn!(error = %err, "failed to read request body");
+                    return Ok(Response::builder()
+                        .status(StatusCode::BAD_REQUEST)
+                        .body(Body::empty())
+                        .expect("response should be valid"));
+                }
+            };
+
+            let delivery = RecordedDelivery {
+                timestamp: Utc::now(),
+                headers: parts
+                    .headers
+                    .iter()
+                    .filter_map(|(name, value)| {
+                        Some((name.to_string(), value.to_str().ok()?.into()))
+                    })
+                    .collect(),
+                body: body.to_vec(),
+            };
+
+            // Writing to the file blocks, so it is done on another thread.
+            let result = {
+                let recording = recording.clone();
+                tokio::task::spawn_blocking(move || recording.append(&delivery))
+                    .await
+            };
+
+            match result {
+                Ok(Ok(())) => {}
+                Ok(Err(err)) => tracing::error!(
+                    error = %err,
+                    path = %recording.path.display(),
+                    "failed to record delivery"
+                ),
+                Err(err) => tracing::error!(
+                    error = %err,
+                    path = %recording.path.display(),
+                    "recording task failed"
+                ),
+            }
+
+            let req = Request::from_parts(parts, Body::from(body));
+            service.call(req).await
+        })
+    }
+}
+
+/// (De)serializes the body of a [`RecordedDelivery`] as a base64 string.
+mod base64_body {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S>(body: &[u8], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&base64::encode(body))
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<u8>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let encoded = String::deserialize(deserializer)?;
+        base64::decode(&encoded).map_err(D::Error::custom)
+    }
+}
@@ -0,0 +1,233 @@
+// Copyright (c) 2019 Jason White
+// Copyright (c) 2019 Mike Lubinets
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//! Redelivery of failed webhook deliveries through the app's delivery log.
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use reqwest::Method;
+use serde::Deserialize;
+
+use crate::{ApiError, ClientPool};
+
+/// An entry in the delivery log of the app's webhook.
+#[derive(Debug, Clone, Deserialize)]
+pub struct HookDelivery {
+    /// The ID of this delivery attempt.
+    pub id: u64,
+
+    /// The delivery ID. This is the same for all attempts at delivering the
+    /// same event.
+    pub guid: String,
+
+    pub delivered_at: DateTime<Utc>,
+
+    /// Whether this attempt was a redelivery.
+    pub redelivery: bool,
+
+    /// A description of the result (e.g., `OK` or `Invalid HTTP Response`).
+    pub status: String,
+
+    /// The status code the app responded with, or 0 if it did not respond.
+    pub status_code: u16,
+
+    pub event: String,
+    pub action: Option<String>,
+    pub installation_id: Option<u64>,
+    pub repository_id: Option<u64>,
+}
+
+impl HookDelivery {
+    /// Returns `true` if the app responded with a success status.
+    pub fn is_success(&self) -> bool {
+        self.status_code >= 200 && self.status_code < 300
+    }
+}
+
+impl ClientPool {
+    /// Lists the delivery attempts made since the given time, newest first.
+    pub async fn hook_deliveries(
+        &self,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<HookDelivery>, ApiError> {
+        let mut deliveries = Vec::new();
+        let mut page = Some(String::from("/app/hook/deliveries?per_page=100"));
+
+        while let Some(path) = page {
+            let response = self.app_request(Method::GET, &path, None).await?;
+
+            let batch: Vec<HookDelivery> =
+                serde_json::from_value(response.body).map_err(|err| {
+                    ApiError::Status {
+                        status: response.status,
+                        message: format!("Invalid delivery log: {}", err),
+                    }
+                })?;
+
+            let done = batch.iter().any(|d| d.delivered_at < since);
+
+            deliveries
+                .extend(batch.into_iter().filter(|d| d.delivered_at >= since));
+
+            page = if done { None } else { response.next };
+        }
+
+        Ok(deliveries)
+    }
+
+    /// Asks GitHub to deliver the delivery attempt with the given ID again.
+    pub async fn redeliver(&self, id: u64) -> Result<(), ApiError> {
+        let path = format!("/app/hook/deliveries/{}/attempts", id);
+        self.app_request(Method::POST, &path, None).await?;
+        Ok(())
+    }
+}
+
+/// Redelivers the deliveries that failed during a period of time, such as an
+/// outage of the app.
+///
+/// A delivery is considered failed if none of the attempts at delivering it
+/// succeeded, so running this again does not redeliver events that have
+/// already been caught up on.
+///
+/// # Example
+///
+/// ```no_run
+/// # use github_app::{ApiError, ClientPool};
+/// # async fn example(pool: ClientPool) -> Result<(), ApiError> {
+/// use chrono::{Duration, Utc};
+/// use github_app::Redelivery;
+///
+/// let failed = Redelivery::new(Utc::now() - Duration::hours(6))
+///     .dry_run(true)
+///     .run(&pool)
+///     .await?;
+///
+/// for delivery in failed {
+///     println!("{} {}", delivery.guid, delivery.event);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Redelivery {
+    since: DateTime<Utc>,
+    until: Option<DateTime<Utc>>,
+    dry_run: bool,
+    interval: Duration,
+}
+
+impl Redelivery {
+    /// Redelivers deliveries that failed since the given time.
+    pub fn new(since: DateTime<Utc>) -> Self {
+        Redelivery {
+            since,
+            until: None,
+            dry_run: false,
+            interval: Duration::from_millis(500),
+        }
+    }
+
+    /// Only redelivers deliveries that failed before the given time.
+    pub fn until(mut self, until: DateTime<Utc>) -> Self {
+        self.until = Some(until);
+        self
+    }
+
+    /// If enabled, the failed deliveries are only listed.
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Sets how long to wait between redelivery requests so that GitHub's
+    /// rate limits are not hit. Defaults to 500ms.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Finds the failed deliveries and, unless this is a dry run, requests
+    /// their redelivery. Returns the failed deliveries, oldest first.
+    pub async fn run(
+        &self,
+        pool: &ClientPool,
+    ) -> Result<Vec<HookDelivery>, ApiError> {
+        let failed = self.failed(pool.hook_deliveries(self.since).await?);
+
+        if self.dry_run {
+            return Ok(failed);
+        }
+
+        for (i, delivery) in failed.iter().enumerate() {
+            if i > 0 {
+                tokio::time::delay_for(self.interval).await;
+            }
+
+            tracing::info!(
+                delivery = %delivery.guid,
+                event = %delivery.event,
+                "requesting redelivery"
+            );
+
+            pool.redeliver(delivery.id).await?;
+        }
+
+        Ok(failed)
+    }
+
+    /// Picks the most recent attempt of each delivery that never succeeded.
+    fn failed(&self, attempts: Vec<HookDelivery>) -> Vec<HookDelivery> {
+        let mut latest: HashMap<String, HookDelivery> = HashMap::new();
+        let mut first: HashMap<String, DateTime<Utc>> = HashMap::new();
+        let mut succeeded = HashSet::new();
+
+        for attempt in attempts {
+            if attempt.is_success() {
+                succeeded.insert(attempt.guid.clone());
+            }
+
+            let time = first
+                .entry(attempt.guid.clone())
+                .or_insert(attempt.delivered_at);
+            *time = (*time).min(attempt.delivered_at);
+
+            match latest.get(&attempt.guid) {
+                Some(d) if d.delivered_at >= attempt.delivered_at => {}
+                _ => {
+                    latest.insert(attempt.guid.clone(), attempt);
+                }
+            }
+        }
+
+        let mut failed: Vec<_> = latest
+            .into_values()
+            .filter(|d| !succeeded.contains(&d.guid))
+            .filter(|d| match self.until {
+                Some(until) => first[&d.guid] < until,
+                None => true,
+            })
+            .collect();
+
+        failed.sort_by_key(|d| first[&d.guid]);
+        failed
+    }
+}
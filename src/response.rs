@@ -0,0 +1,76 @@
+// Copyright (c) 2019 Jason White
+// Copyright (c) 2019 Mike Lubinets
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+use std::convert::Infallible;
+use std::error::Error as StdError;
+use std::fmt;
+use std::io;
+
+use hyper::{header::HeaderMap, http::StatusCode};
+
+use crate::{ApiError, ConfigError};
+
+/// Describes how an error is turned into the HTTP response that is sent back
+/// to GitHub.
+///
+/// GitHub shows the status and body of the response in the delivery log of the
+/// app, so this is also a good place to give a short explanation of what went
+/// wrong. By default, errors are reported as `500 Internal Server Error` with
+/// the `Display` string of the error as the body.
+///
+/// This is implemented for the error types of this crate and of the crates it
+/// re-exports (e.g., `hubcaps::Error`), as well as for
+/// `Box<dyn Error + Send + Sync>`. Because of the orphan rule, other foreign
+/// error types (such as `anyhow::Error`) have to be converted into one of
+/// these or wrapped in a newtype.
+pub trait IntoResponse: fmt::Display {
+    /// The status code of the response.
+    fn status(&self) -> StatusCode {
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
+
+    /// The body of the response.
+    fn body(&self) -> String {
+        self.to_string()
+    }
+
+    /// Additional headers to include in the response (e.g., `Retry-After`).
+    fn headers(&self) -> HeaderMap {
+        HeaderMap::new()
+    }
+}
+
+impl IntoResponse for io::Error {}
+
+impl IntoResponse for String {}
+
+impl IntoResponse for &'static str {}
+
+impl IntoResponse for Box<dyn StdError + Send + Sync> {}
+
+impl IntoResponse for Infallible {}
+
+impl IntoResponse for serde_json::Error {}
+
+impl IntoResponse for hubcaps::Error {}
+
+impl IntoResponse for ApiError {}
+
+impl IntoResponse for ConfigError {}
@@ -0,0 +1,588 @@
+// Copyright (c) 2019 Jason White
+// Copyright (c) 2019 Mike Lubinets
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//! Sources of webhook deliveries other than GitHub calling the app directly.
+//!
+//! An app does not have to be reachable from GitHub. Deliveries can also be
+//! received by something else (e.g., a relay that puts them on a queue) and
+//! handed to the app later. A [`Source`] produces such deliveries and [`run`]
+//! feeds them through an [`App`], so that they go through the same signature
+//! verification, limits and middleware as a delivery received over HTTP.
+//!
+//! The included sources are:
+//!
+//!  * [`HttpSource`], an HTTP server like the one started by
+//!    [`serve`](crate::serve).
+//!  * [`Spool`], a directory that deliveries are written to as files.
+//!  * [`JsonLines`], a stream of deliveries in JSONL format, such as stdin.
+//!  * [`StreamSource`], any stream of headers and payloads.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # async fn example<T>(app: T) -> std::io::Result<()>
+//! # where
+//! #     T: github_app::GithubApp + Sync + Send + 'static,
+//! #     T::Error: Send,
+//! # {
+//! use github_app::{source, App, DedupLayer, GithubAppExt, Spool};
+//!
+//! let app = App::new(app.layer(DedupLayer::new(1024)));
+//!
+//! source::run(Spool::new("/var/spool/webhooks"), app, 4).await
+//! # }
+//! ```
+use std::collections::{HashSet, VecDeque};
+use std::convert::Infallible;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures::channel::{mpsc, oneshot};
+use futures::future::poll_fn;
+use futures::stream::{self, BoxStream};
+use futures::{FutureExt, SinkExt, Stream, StreamExt, TryStreamExt};
+use hyper::{
+    header::HeaderMap,
+    http::{Method, StatusCode},
+    service::{make_service_fn, service_fn, Service},
+    Body, Request, Response, Server,
+};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, BufReader, Stdin};
+
+use crate::{record::RecordedDelivery, App, GithubApp, WebhookRequest};
+
+/// A delivery produced by a [`Source`].
+///
+/// A delivery can carry an acknowledgement that is called with the status of
+/// the app's response once it has been handled. Sources use this to, for
+/// example, remove a delivery from a queue only after it succeeded.
+pub struct Incoming {
+    pub request: WebhookRequest,
+    ack: Option<Box<dyn FnOnce(StatusCode) + Send>>,
+}
+
+impl Incoming {
+    pub fn new(request: WebhookRequest) -> Self {
+        Incoming { request, ack: None }
+    }
+
+    /// Creates a delivery with an acknowledgement.
+    pub fn with_ack<F>(request: WebhookRequest, ack: F) -> Self
+    where
+        F: FnOnce(StatusCode) + Send + 'static,
+    {
+        Incoming {
+            request,
+            ack: Some(Box::new(ack)),
+        }
+    }
+}
+
+impl fmt::Debug for Incoming {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Incoming")
+            .field("request", &self.request)
+            .field("ack", &self.ack.is_some())
+            .finish()
+    }
+}
+
+impl From<WebhookRequest> for Incoming {
+    fn from(request: WebhookRequest) -> Self {
+        Incoming::new(request)
+    }
+}
+
+impl From<(HeaderMap, Vec<u8>)> for Incoming {
+    fn from((headers, body): (HeaderMap, Vec<u8>)) -> Self {
+        Incoming::new(WebhookRequest { headers, body })
+    }
+}
+
+/// Something that produces webhook deliveries.
+pub trait Source {
+    /// Turns the source into a stream of deliveries. The stream ends when
+    /// the source is exhausted. An error is fatal and ends [`run`].
+    fn into_stream(self) -> BoxStream<'static, io::Result<Incoming>>;
+}
+
+/// Feeds every delivery from a source through the app, handling at most
+/// `concurrency` of them at a time. A concurrency of zero means no limit.
+///
+/// Deliveries that the app fails to handle are logged and acknowledged with
+/// the status of the response, but do not stop the source.
+pub async fn run<S, T>(
+    source: S,
+    app: App<T>,
+    concurrency: usize,
+) -> io::Result<()>
+where
+    S: Source,
+    T: GithubApp + Sync + Send + 'static,
+{
+    source
+        .into_stream()
+        .try_for_each_concurrent(concurrency, move |incoming| {
+            deliver(app.clone(), incoming).map(Ok)
+        })
+        .await
+}
+
+async fn deliver<T>(mut app: App<T>, incoming: Incoming)
+where
+    T: GithubApp + Sync + Send + 'static,
+{
+    let Incoming { request, ack } = incoming;
+
+    let id = request
+        .headers
+        .get("X-GitHub-Delivery")
+        .and_then(|header| header.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+
+    let status = match send(&mut app, request).await {
+        Ok(status) => status,
+        Err(err) => {
+            tracing::error!(id = %id, error = %err, "failed to send delivery");
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    };
+
+    if !status.is_success() {
+        tracing::warn!(id = %id, status = %status, "delivery failed");
+    }
+
+    if let Some(ack) = ack {
+        ack(status);
+    }
+}
+
+async fn send<T>(
+    app: &mut App<T>,
+    request: WebhookRequest,
+) -> Result<StatusCode, hyper::http::Error>
+where
+    T: GithubApp + Sync + Send + 'static,
+{
+    let mut req = Request::builder()
+        .method(Method::POST)
+        .uri("/")
+        .body(Body::from(request.body))?;
+
+    *req.headers_mut() = request.headers;
+
+    poll_fn(|cx| app.poll_ready(cx)).await?;
+    Ok(app.call(req).await?.status())
+}
+
+/// Deliveries received by an HTTP server.
+///
+/// Each request is answered with the status of the app's response once the
+/// delivery has been handled. Unlike [`serve`](crate::serve), the response
+/// body is always empty.
+#[derive(Debug, Clone)]
+pub struct HttpSource {
+    addr: SocketAddr,
+}
+
+impl HttpSource {
+    /// Listens on the given address.
+    pub fn bind(addr: SocketAddr) -> Self {
+        HttpSource { addr }
+    }
+}
+
+impl Source for HttpSource {
+    fn into_stream(self) -> BoxStream<'static, io::Result<Incoming>> {
+        let builder = match Server::try_bind(&self.addr) {
+            Ok(builder) => builder,
+            Err(err) => {
+                let err = io::Error::new(io::ErrorKind::Other, err);
+                return stream::once(async { Err(err) }).boxed();
+            }
+        };
+
+        let (tx, rx) = mpsc::channel(0);
+
+        let make_service = {
+            let tx = tx.clone();
+
+            make_service_fn(move |_| {
+                let tx = tx.clone();
+
+                async move {
+                    Ok::<_, Infallible>(service_fn(move |req| {
+                        receive(tx.clone(), req)
+                    }))
+                }
+            })
+        };
+
+        let server = builder.serve(make_service);
+
+        tracing::info!("Listening on {}", server.local_addr());
+
+        tokio::spawn(async move {
+            if let Err(err) = server.await {
+                let err = io::Error::new(io::ErrorKind::Other, err);
+                let _ = tx.clone().send(Err(err)).await;
+            }
+        });
+
+        rx.boxed()
+    }
+}
+
+async fn receive(
+    mut tx: mpsc::Sender<io::Result<Incoming>>,
+    req: Request<Body>,
+) -> Result<Response<Body>, hyper::http::Error> {
+    let (parts, body) = req.into_parts();
+
+    let status = match hyper::body::to_bytes(body).await {
+        Ok(body) => {
+            let request = WebhookRequest {
+                headers: parts.headers,
+                body: body.to_vec(),
+            };
+
+            let (ack, status) = oneshot::channel();
+            let incoming = Incoming::with_ack(request, move |status| {
+                let _ = ack.send(status);
+            });
+
+            // If the source is no longer being run, the delivery is dropped
+            // along with its acknowledgement.
+            if tx.send(Ok(incoming)).await.is_ok() {
+                status.await.unwrap_or(StatusCode::SERVICE_UNAVAILABLE)
+            } else {
+                StatusCode::SERVICE_UNAVAILABLE
+            }
+        }
+        Err(err) => {
+            tracing::warn!(error = %err, "failed to read request body");
+            StatusCode::BAD_REQUEST
+        }
+    };
+
+    Response::builder().status(status).body(Body::empty())
+}
+
+/// Deliveries spooled to a directory.
+///
+/// Every file with a `.json` extension holds a single delivery in the format
+/// of a [`RecordedDelivery`]. The files are handled in the order of their
+/// names. To avoid reading partially written files, they should be written
+/// under a different name first and then renamed.
+///
+/// A file is removed once its delivery was handled successfully. Otherwise,
+/// it is moved to a `failed` subdirectory so that it can be inspected and
+/// spooled again.
+#[derive(Debug, Clone)]
+pub struct Spool {
+    dir: PathBuf,
+    failed: PathBuf,
+    poll_interval: Duration,
+    watch: bool,
+}
+
+impl Spool {
+    pub fn new<P>(dir: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        let dir = dir.into();
+
+        Spool {
+            failed: dir.join("failed"),
+            dir,
+            poll_interval: Duration::from_secs(1),
+            watch: true,
+        }
+    }
+
+    /// The directory that failed deliveries are moved to. Defaults to
+    /// `failed` in the spool directory.
+    pub fn failed_dir<P>(mut self, dir: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        self.failed = dir.into();
+        self
+    }
+
+    /// How often the directory is checked for new files when it is empty.
+    /// Defaults to one second.
+    pub fn poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// Whether to keep waiting for new files once the directory is empty.
+    /// Defaults to `true`. If disabled, the source ends instead, which is
+    /// useful for draining a spool from a cron job.
+    pub fn watch(mut self, watch: bool) -> Self {
+        self.watch = watch;
+        self
+    }
+}
+
+impl Source for Spool {
+    fn into_stream(self) -> BoxStream<'static, io::Result<Incoming>> {
+        let reader = SpoolReader {
+            spool: Arc::new(self),
+            pending: VecDeque::new(),
+            in_flight: Arc::new(Mutex::new(HashSet::new())),
+        };
+
+        stream::unfold(Some(reader), |reader| async move {
+            let mut reader = reader?;
+
+            match reader.next().await {
+                Ok(Some(incoming)) => Some((Ok(incoming), Some(reader))),
+                Ok(None) => None,
+                Err(err) => Some((Err(err), None)),
+            }
+        })
+        .boxed()
+    }
+}
+
+struct SpoolReader {
+    spool: Arc<Spool>,
+    pending: VecDeque<PathBuf>,
+
+    // Files that are being handled. These are still in the directory and
+    // must not be picked up again.
+    in_flight: Arc<Mutex<HashSet<PathBuf>>>,
+}
+
+impl SpoolReader {
+    async fn next(&mut self) -> io::Result<Option<Incoming>> {
+        loop {
+            while let Some(path) = self.pending.pop_front() {
+                if let Some(incoming) = self.open(path) {
+                    return Ok(Some(incoming));
+                }
+            }
+
+            self.scan()?;
+
+            if self.pending.is_empty() {
+                if !self.spool.watch {
+                    return Ok(None);
+                }
+
+                tokio::time::delay_for(self.spool.poll_interval).await;
+            }
+        }
+    }
+
+    fn scan(&mut self) -> io::Result<()> {
+        let in_flight = self.in_flight.lock().unwrap();
+        let mut paths = Vec::new();
+
+        for entry in fs::read_dir(&self.spool.dir)? {
+            let entry = entry?;
+            let path = entry.path();
+
+            let spooled = entry.file_type()?.is_file()
+                && path.extension().map_or(false, |ext| ext == "json")
+                && !entry.file_name().to_string_lossy().starts_with('.');
+
+            if spooled && !in_flight.contains(&path) {
+                paths.push(path);
+            }
+        }
+
+        paths.sort();
+        self.pending.extend(paths);
+
+        Ok(())
+    }
+
+    fn open(&mut self, path: PathBuf) -> Option<Incoming> {
+        let request = match read_spooled(&path) {
+            Ok(request) => request,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return None,
+            Err(err) => {
+                tracing::error!(
+                    error = %err,
+                    path = %path.display(),
+                    "invalid spooled delivery"
+                );
+                fail(&self.spool.failed, &path);
+                return None;
+            }
+        };
+
+        self.in_flight.lock().unwrap().insert(path.clone());
+
+        let spool = self.spool.clone();
+        let in_flight = self.in_flight.clone();
+
+        Some(Incoming::with_ack(request, move |status| {
+            if status.is_success() {
+                if let Err(err) = fs::remove_file(&path) {
+                    tracing::error!(
+                        error = %err,
+                        path = %path.display(),
+                        "failed to remove spooled delivery"
+                    );
+                }
+            } else {
+                fail(&spool.failed, &path);
+            }
+
+            in_flight.lock().unwrap().remove(&path);
+        }))
+    }
+}
+
+fn read_spooled(path: &Path) -> io::Result<WebhookRequest> {
+    let delivery: RecordedDelivery = serde_json::from_slice(&fs::read(path)?)?;
+
+    delivery
+        .into_webhook_request()
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+fn fail(failed: &Path, path: &Path) {
+    let moved = fs::create_dir_all(failed).and_then(|()| {
+        fs::rename(path, failed.join(path.file_name().unwrap_or_default()))
+    });
+
+    if let Err(err) = moved {
+        tracing::error!(
+            error = %err,
+            path = %path.display(),
+            "failed to move spooled delivery"
+        );
+    }
+}
+
+/// Deliveries read line by line, in the format of a [`RecordedDelivery`].
+/// This is the same format that a [`Recording`](crate::Recording) is written
+/// in. Empty lines are skipped and invalid lines are logged and skipped.
+#[derive(Debug)]
+pub struct JsonLines<R> {
+    reader: R,
+}
+
+impl<R> JsonLines<R>
+where
+    R: AsyncBufRead + Unpin + Send + 'static,
+{
+    pub fn new(reader: R) -> Self {
+        JsonLines { reader }
+    }
+}
+
+impl JsonLines<BufReader<Stdin>> {
+    /// Reads deliveries from stdin until it is closed.
+    pub fn stdin() -> Self {
+        JsonLines::new(BufReader::new(tokio::io::stdin()))
+    }
+}
+
+impl<R> Source for JsonLines<R>
+where
+    R: AsyncBufRead + Unpin + Send + 'static,
+{
+    fn into_stream(self) -> BoxStream<'static, io::Result<Incoming>> {
+        let lines = self.reader.lines();
+
+        stream::unfold(Some((lines, 0)), |state| async move {
+            let (mut lines, mut number) = state?;
+
+            loop {
+                number += 1;
+
+                let line = match lines.next_line().await {
+                    Ok(Some(line)) => line,
+                    Ok(None) => return None,
+                    Err(err) => return Some((Err(err), None)),
+                };
+
+                if line.trim().is_empty() {
+                    continue;
+                }
+
+                let request = serde_json::from_str::<RecordedDelivery>(&line)
+                    .map_err(|err| err.to_string())
+                    .and_then(|delivery| {
+                        delivery
+                            .into_webhook_request()
+                            .map_err(|err| err.to_string())
+                    });
+
+                match request {
+                    Ok(request) => {
+                        let incoming = Incoming::new(request);
+                        return Some((Ok(incoming), Some((lines, number))));
+                    }
+                    Err(err) => {
+                        tracing::error!(
+                            error = %err,
+                            line = number,
+                            "invalid delivery"
+                        );
+                    }
+                }
+            }
+        })
+        .boxed()
+    }
+}
+
+/// Deliveries from any stream, such as the consumer of a message queue.
+///
+/// The items can be anything that converts into an [`Incoming`] delivery,
+/// like a [`WebhookRequest`] or a tuple of headers and payload. The headers
+/// should be the ones GitHub sent the delivery with.
+pub struct StreamSource<S> {
+    stream: S,
+}
+
+impl<S> StreamSource<S>
+where
+    S: Stream + Send + 'static,
+    S::Item: Into<Incoming>,
+{
+    pub fn new(stream: S) -> Self {
+        StreamSource { stream }
+    }
+}
+
+impl<S> Source for StreamSource<S>
+where
+    S: Stream + Send + 'static,
+    S::Item: Into<Incoming>,
+{
+    fn into_stream(self) -> BoxStream<'static, io::Result<Incoming>> {
+        self.stream.map(|item| Ok(item.into())).boxed()
+    }
+}
@@ -0,0 +1,219 @@
+// Copyright (c) 2019 Jason White
+// Copyright (c) 2019 Mike Lubinets
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//! Comments that are created once and then updated in place.
+use reqwest::Method;
+use serde_json::{json, Value};
+
+use crate::{ApiError, ClientPool};
+
+/// Separates the current content of a sticky comment from the collapsed
+/// previous content.
+const PREVIOUS: &str = "<!-- github-app:previous -->";
+
+/// A bot comment on an issue or pull request that is updated instead of
+/// posting a new comment every time.
+///
+/// The comment is found again through a hidden marker (an HTML comment) that
+/// includes a key, so an app can keep several sticky comments on the same
+/// issue. Only comments by the app's own bot account are considered, so that
+/// neither users nor other apps can take over the comment by copying the
+/// marker.
+///
+/// # Example
+///
+/// ```no_run
+/// # use github_app::{ApiError, ClientPool};
+/// # async fn example(pool: &ClientPool) -> Result<(), ApiError> {
+/// use github_app::StickyComment;
+///
+/// let comment = StickyComment::new("coverage").collapse_previous(true);
+///
+/// comment
+///     .upsert(pool, 1, "octocat/hello-world", 42, "Coverage: 87%")
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct StickyComment {
+    marker: String,
+    collapse_previous: bool,
+}
+
+impl StickyComment {
+    /// Creates a sticky comment with the given key.
+    pub fn new(key: &str) -> Self {
+        StickyComment {
+            marker: format!("<!-- github-app:sticky:{} -->", key),
+            collapse_previous: false,
+        }
+    }
+
+    /// If enabled, the previous content is kept in a collapsed section when
+    /// the comment is updated. Only the most recent previous content is kept.
+    pub fn collapse_previous(mut self, collapse: bool) -> Self {
+        self.collapse_previous = collapse;
+        self
+    }
+
+    /// Creates the comment or, if it already exists, replaces its content.
+    /// Returns the ID of the comment.
+    pub async fn upsert(
+        &self,
+        pool: &ClientPool,
+        installation: u64,
+        repository: &str,
+        number: u64,
+        content: &str,
+    ) -> Result<u64, ApiError> {
+        let existing =
+            self.find(pool, installation, repository, number).await?;
+
+        let (method, path, body) = match existing {
+            Some((id, old)) => {
+                let old = self.content(&old);
+
+                if old == content.trim() {
+                    // Avoid notifying subscribers about a non-change.
+                    return Ok(id);
+                }
+
+                let body = if self.collapse_previous && !old.is_empty() {
+                    format!(
+                        "{}\n{}\n\n{}\n<details><summary>Previous \
+                         version</summary>\n\n{}\n\n</details>",
+                        self.marker,
+                        content.trim(),
+                        PREVIOUS,
+                        old
+                    )
+                } else {
+                    self.body(content)
+                };
+
+                let path =
+                    format!("/repos/{}/issues/comments/{}", repository, id);
+                (Method::PATCH, path, body)
+            }
+            None => {
+                let path =
+                    format!("/repos/{}/issues/{}/comments", repository, number);
+                (Method::POST, path, self.body(content))
+            }
+        };
+
+        let response = pool
+            .installation_request(
+                installation,
+                method,
+                &path,
+                Some(&json!({ "body": body })),
+            )
+            .await?;
+
+        response.body["id"]
+            .as_u64()
+            .ok_or_else(|| ApiError::Status {
+                status: response.status,
+                message: "Missing comment ID".into(),
+            })
+    }
+
+    /// Deletes the comment if it exists. Returns `true` if it was deleted.
+    pub async fn delete(
+        &self,
+        pool: &ClientPool,
+        installation: u64,
+        repository: &str,
+        number: u64,
+    ) -> Result<bool, ApiError> {
+        match self.find(pool, installation, repository, number).await? {
+            Some((id, _)) => {
+                let path =
+                    format!("/repos/{}/issues/comments/{}", repository, id);
+                pool.installation_request(
+                    installation,
+                    Method::DELETE,
+                    &path,
+                    None,
+                )
+                .await?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Finds the ID and body of the comment.
+    pub async fn find(
+        &self,
+        pool: &ClientPool,
+        installation: u64,
+        repository: &str,
+        number: u64,
+    ) -> Result<Option<(u64, String)>, ApiError> {
+        let login = pool.bot_login().await?;
+
+        let mut page = Some(format!(
+            "/repos/{}/issues/{}/comments?per_page=100",
+            repository, number
+        ));
+
+        while let Some(path) = page {
+            let response = pool
+                .installation_request(installation, Method::GET, &path, None)
+                .await?;
+
+            let comments = response.body.as_array().into_iter().flatten();
+
+            if let Some(found) =
+                comments.filter_map(|c| self.matches(c, &login)).next()
+            {
+                return Ok(Some(found));
+            }
+
+            page = response.next;
+        }
+
+        Ok(None)
+    }
+
+    fn matches(&self, comment: &Value, login: &str) -> Option<(u64, String)> {
+        let body = comment["body"].as_str()?;
+
+        if comment["user"]["login"] != login || !body.starts_with(&self.marker)
+        {
+            return None;
+        }
+
+        Some((comment["id"].as_u64()?, body.to_string()))
+    }
+
+    fn body(&self, content: &str) -> String {
+        format!("{}\n{}", self.marker, content.trim())
+    }
+
+    /// Extracts the current content from the body of the comment.
+    fn content<'a>(&self, body: &'a str) -> &'a str {
+        let body = body.get(self.marker.len()..).unwrap_or_default();
+        body.split(PREVIOUS).next().unwrap_or_default().trim()
+    }
+}
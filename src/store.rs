@@ -0,0 +1,388 @@
+// Copyright (c) 2019 Jason White
+// Copyright (c) 2019 Mike Lubinets
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//! Persistent state for apps.
+//!
+//! Apps often need to remember things between deliveries, such as the ID of a
+//! comment to update later. A [`StateStore`] keeps JSON values under a key
+//! within a [`Scope`] (an installation, a repository or an issue).
+//!
+//! A [`ScopedStore`] is a store bound to the scope of a delivery. `Commands`
+//! and `Checks` that were given a store pass one to every handler in its
+//! context. Other apps can get one with [`ScopedStore::for_delivery`].
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{Map, Value};
+
+use crate::Delivery;
+
+/// What a value in a [`StateStore`] belongs to.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Scope {
+    installation: u64,
+    repository: Option<String>,
+    issue: Option<u64>,
+}
+
+impl Scope {
+    /// A scope for the whole installation.
+    pub fn installation(installation: u64) -> Self {
+        Scope {
+            installation,
+            repository: None,
+            issue: None,
+        }
+    }
+
+    /// A scope for a repository, given by its full name (e.g.,
+    /// `octocat/hello-world`).
+    pub fn repository<S>(installation: u64, repository: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Scope {
+            installation,
+            repository: Some(repository.into()),
+            issue: None,
+        }
+    }
+
+    /// A scope for an issue or pull request.
+    pub fn issue<S>(installation: u64, repository: S, number: u64) -> Self
+    where
+        S: Into<String>,
+    {
+        Scope {
+            installation,
+            repository: Some(repository.into()),
+            issue: Some(number),
+        }
+    }
+
+    /// The narrowest scope of a delivery. Returns `None` if the delivery was
+    /// not sent to an installation.
+    pub fn from_delivery(delivery: &Delivery) -> Option<Self> {
+        Some(Scope {
+            installation: delivery.installation?,
+            repository: delivery.repository.clone(),
+            issue: delivery.repository.as_ref().and(delivery.number),
+        })
+    }
+
+    pub fn installation_id(&self) -> u64 {
+        self.installation
+    }
+
+    pub fn repository_name(&self) -> Option<&str> {
+        self.repository.as_deref()
+    }
+
+    pub fn issue_number(&self) -> Option<u64> {
+        self.issue
+    }
+}
+
+/// Storage for values that need to outlive a single delivery.
+///
+/// The methods are blocking. Implementations are expected to be fast enough
+/// to be called directly from an app's future.
+pub trait StateStore: Send + Sync {
+    /// Gets the value of a key.
+    fn get(&self, scope: &Scope, key: &str) -> io::Result<Option<Value>>;
+
+    /// Sets the value of a key, replacing the previous value.
+    fn set(&self, scope: &Scope, key: &str, value: Value) -> io::Result<()>;
+
+    /// Removes a key, returning its previous value.
+    fn remove(&self, scope: &Scope, key: &str) -> io::Result<Option<Value>>;
+}
+
+/// Typed access to a [`StateStore`].
+pub trait StateStoreExt: StateStore {
+    /// Gets the value of a key and deserializes it.
+    fn load<T>(&self, scope: &Scope, key: &str) -> io::Result<Option<T>>
+    where
+        T: DeserializeOwned,
+    {
+        match self.get(scope, key)? {
+            Some(value) => Ok(Some(serde_json::from_value(value)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Serializes a value and stores it under a key.
+    fn store<T>(&self, scope: &Scope, key: &str, value: &T) -> io::Result<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.set(scope, key, serde_json::to_value(value)?)
+    }
+}
+
+impl<S> StateStore for Arc<S>
+where
+    S: StateStore + ?Sized,
+{
+    fn get(&self, scope: &Scope, key: &str) -> io::Result<Option<Value>> {
+        (**self).get(scope, key)
+    }
+
+    fn set(&self, scope: &Scope, key: &str, value: Value) -> io::Result<()> {
+        (**self).set(scope, key, value)
+    }
+
+    fn remove(&self, scope: &Scope, key: &str) -> io::Result<Option<Value>> {
+        (**self).remove(scope, key)
+    }
+}
+
+impl<S> StateStoreExt for S where S: StateStore + ?Sized {}
+
+/// A [`StateStore`] bound to a scope, such as the issue a delivery is about.
+#[derive(Clone)]
+pub struct ScopedStore {
+    store: Arc<dyn StateStore>,
+    scope: Scope,
+}
+
+impl ScopedStore {
+    pub fn new(store: Arc<dyn StateStore>, scope: Scope) -> Self {
+        ScopedStore { store, scope }
+    }
+
+    /// Binds the store to the narrowest scope of a delivery. Returns `None`
+    /// if the delivery was not sent to an installation.
+    pub fn for_delivery(
+        store: Arc<dyn StateStore>,
+        delivery: &Delivery,
+    ) -> Option<Self> {
+        Some(ScopedStore::new(store, Scope::from_delivery(delivery)?))
+    }
+
+    pub fn scope(&self) -> &Scope {
+        &self.scope
+    }
+
+    /// The same store, bound to another scope (e.g., the whole installation).
+    pub fn with_scope(&self, scope: Scope) -> Self {
+        ScopedStore::new(self.store.clone(), scope)
+    }
+
+    pub fn get(&self, key: &str) -> io::Result<Option<Value>> {
+        self.store.get(&self.scope, key)
+    }
+
+    pub fn set(&self, key: &str, value: Value) -> io::Result<()> {
+        self.store.set(&self.scope, key, value)
+    }
+
+    pub fn remove(&self, key: &str) -> io::Result<Option<Value>> {
+        self.store.remove(&self.scope, key)
+    }
+
+    /// Gets the value of a key and deserializes it.
+    pub fn load<T>(&self, key: &str) -> io::Result<Option<T>>
+    where
+        T: DeserializeOwned,
+    {
+        self.store.load(&self.scope, key)
+    }
+
+    /// Serializes a value and stores it under a key.
+    pub fn store<T>(&self, key: &str, value: &T) -> io::Result<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.store.store(&self.scope, key, value)
+    }
+}
+
+impl fmt::Debug for ScopedStore {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ScopedStore")
+            .field("scope", &self.scope)
+            .finish()
+    }
+}
+
+/// A store that keeps everything in memory. The state is lost when the
+/// process exits, which makes this mostly useful for tests.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    values: Mutex<HashMap<Scope, Map<String, Value>>>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl StateStore for MemoryStore {
+    fn get(&self, scope: &Scope, key: &str) -> io::Result<Option<Value>> {
+        let values = self.values.lock().unwrap();
+        Ok(values.get(scope).and_then(|map| map.get(key)).cloned())
+    }
+
+    fn set(&self, scope: &Scope, key: &str, value: Value) -> io::Result<()> {
+        let mut values = self.values.lock().unwrap();
+        values
+            .entry(scope.clone())
+            .or_default()
+            .insert(key.into(), value);
+        Ok(())
+    }
+
+    fn remove(&self, scope: &Scope, key: &str) -> io::Result<Option<Value>> {
+        let mut values = self.values.lock().unwrap();
+        Ok(values.get_mut(scope).and_then(|map| map.remove(key)))
+    }
+}
+
+/// A store that keeps the values of each scope in a JSON file below a
+/// directory.
+///
+/// The files are laid out as `<installation>/state.json`,
+/// `<installation>/<owner>/<repo>/state.json` and
+/// `<installation>/<owner>/<repo>/<issue>.json`, with unusual characters in
+/// the owner and repository names percent-encoded. Files are replaced
+/// atomically, so a crash never leaves a half-written file behind.
+#[derive(Debug)]
+pub struct FileStore {
+    dir: PathBuf,
+
+    /// Serializes read-modify-write cycles. This does not protect against
+    /// other processes using the same directory.
+    lock: Mutex<()>,
+}
+
+impl FileStore {
+    /// Creates a store in the given directory. The directory is created if it
+    /// does not exist.
+    pub fn new<P>(dir: P) -> io::Result<Self>
+    where
+        P: Into<PathBuf>,
+    {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+
+        Ok(FileStore {
+            dir,
+            lock: Mutex::new(()),
+        })
+    }
+
+    fn path(&self, scope: &Scope) -> PathBuf {
+        let mut path = self.dir.join(scope.installation.to_string());
+
+        if let Some(repository) = &scope.repository {
+            for component in repository.split('/') {
+                path.push(sanitize(component));
+            }
+        }
+
+        match scope.issue {
+            Some(issue) => path.push(format!("{}.json", issue)),
+            None => path.push("state.json"),
+        }
+
+        path
+    }
+
+    fn read(path: &Path) -> io::Result<Map<String, Value>> {
+        match fs::read(path) {
+            Ok(data) => Ok(serde_json::from_slice(&data)?),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Map::new()),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn write(path: &Path, values: &Map<String, Value>) -> io::Result<()> {
+        if values.is_empty() {
+            return match fs::remove_file(path) {
+                Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+                _ => Ok(()),
+            };
+        }
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_vec(values)?)?;
+        fs::rename(tmp, path)
+    }
+}
+
+impl StateStore for FileStore {
+    fn get(&self, scope: &Scope, key: &str) -> io::Result<Option<Value>> {
+        let _lock = self.lock.lock().unwrap();
+        Ok(Self::read(&self.path(scope))?.remove(key))
+    }
+
+    fn set(&self, scope: &Scope, key: &str, value: Value) -> io::Result<()> {
+        let _lock = self.lock.lock().unwrap();
+        let path = self.path(scope);
+        let mut values = Self::read(&path)?;
+        values.insert(key.into(), value);
+        Self::write(&path, &values)
+    }
+
+    fn remove(&self, scope: &Scope, key: &str) -> io::Result<Option<Value>> {
+        let _lock = self.lock.lock().unwrap();
+        let path = self.path(scope);
+        let mut values = Self::read(&path)?;
+        let value = values.remove(key);
+
+        if value.is_some() {
+            Self::write(&path, &values)?;
+        }
+
+        Ok(value)
+    }
+}
+
+/// Makes a repository owner or name safe to use as a path component.
+///
+/// Anything other than ASCII letters, digits, `-`, `_` and non-leading dots is
+/// percent-encoded. Unlike replacing those characters, this never maps two
+/// different names to the same directory.
+fn sanitize(component: &str) -> String {
+    let mut sanitized = String::with_capacity(component.len());
+
+    for (i, byte) in component.bytes().enumerate() {
+        match byte {
+            b'.' if i == 0 => sanitized.push_str("%2E"),
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'.' | b'-' | b'_' => {
+                sanitized.push(byte as char)
+            }
+            _ => sanitized.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+
+    sanitized
+}
@@ -0,0 +1,187 @@
+// Copyright (c) 2019 Jason White
+// Copyright (c) 2019 Mike Lubinets
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//! Utilities for testing apps without running a server.
+//!
+//! This module is only available with the `testing` feature.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # async fn example<T>(app: T) -> std::io::Result<()>
+//! # where
+//! #     T: github_app::GithubApp + Sync + Send + 'static,
+//! # {
+//! use github_app::{testing::TestRequest, App, EventType};
+//!
+//! let mut app = App::new(app);
+//!
+//! let request =
+//!     TestRequest::from_file(EventType::PullRequest, "tests/pr.json")?;
+//!
+//! let response = request.send(&mut app).await.unwrap();
+//! assert_eq!(response.status(), 200);
+//! # Ok(())
+//! # }
+//! ```
+use std::collections::hash_map::RandomState;
+use std::fs;
+use std::hash::{BuildHasher, Hasher};
+use std::io;
+use std::path::Path;
+
+use futures::future::poll_fn;
+use hyper::{header, service::Service, Body, Request, Response};
+
+use crate::{App, EventType, GithubApp, Signature};
+
+mod fake_github;
+
+pub use fake_github::{FakeGithub, ReceivedRequest, FAKE_APP_ID};
+
+/// A webhook request, as GitHub would send it.
+#[derive(Debug, Clone)]
+pub struct TestRequest {
+    event: EventType,
+    id: String,
+    secret: Option<String>,
+    body: Vec<u8>,
+}
+
+impl TestRequest {
+    /// Creates a request for the given type of event with the given JSON
+    /// payload. A random delivery ID is generated.
+    pub fn new<B>(event: EventType, body: B) -> Self
+    where
+        B: Into<Vec<u8>>,
+    {
+        TestRequest {
+            event,
+            id: random_guid(),
+            secret: None,
+            body: body.into(),
+        }
+    }
+
+    /// Creates a request with the JSON payload read from a file.
+    pub fn from_file<P>(event: EventType, path: P) -> io::Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        Ok(Self::new(event, fs::read(path)?))
+    }
+
+    /// Sets the delivery ID.
+    pub fn id<S>(mut self, id: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.id = id.into();
+        self
+    }
+
+    /// Sets the secret used to sign the payload. By default, [`send`] uses the
+    /// secret of the app.
+    ///
+    /// [`send`]: TestRequest::send
+    pub fn secret<S>(mut self, secret: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.secret = Some(secret.into());
+        self
+    }
+
+    /// The delivery ID.
+    pub fn delivery_id(&self) -> &str {
+        &self.id
+    }
+
+    /// The JSON payload.
+    pub fn body(&self) -> &[u8] {
+        &self.body
+    }
+
+    /// Builds the HTTP request. The payload is signed with the secret set with
+    /// [`TestRequest::secret`] or, if there is none, an empty secret.
+    pub fn into_request(self) -> Request<Body> {
+        let signature = Signature::compute(
+            self.secret.as_ref().map_or("", String::as_str),
+            &self.body,
+        );
+
+        Request::post("/")
+            .header(header::CONTENT_TYPE, "application/json")
+            .header("X-GitHub-Event", self.event.to_string())
+            .header("X-GitHub-Delivery", self.id)
+            .header("X-Hub-Signature", signature.to_string())
+            .body(Body::from(self.body))
+            .expect("test request should be valid")
+    }
+
+    /// Sends the request to the app and returns its response.
+    pub async fn send<T>(
+        mut self,
+        app: &mut App<T>,
+    ) -> Result<Response<Body>, hyper::http::Error>
+    where
+        T: GithubApp + Sync + Send + 'static,
+    {
+        if self.secret.is_none() {
+            self.secret = app.app.secret().map(String::from);
+        }
+
+        poll_fn(|cx| app.poll_ready(cx)).await?;
+        app.call(self.into_request()).await
+    }
+}
+
+/// Reads the whole body of a response as a string.
+pub async fn body_string(response: Response<Body>) -> hyper::Result<String> {
+    let body = hyper::body::to_bytes(response.into_body()).await?;
+    Ok(String::from_utf8_lossy(&body).into_owned())
+}
+
+/// Generates a random GUID in the format GitHub uses for delivery IDs.
+fn random_guid() -> String {
+    let id = (u128::from(random_id()) << 64) | u128::from(random_id());
+    let hex = format!("{:032x}", id);
+
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
+
+/// Generates a random, non-zero ID without pulling in a dependency on a
+/// random number generator. Every `RandomState` uses different random keys.
+fn random_id() -> u64 {
+    loop {
+        let id = RandomState::new().build_hasher().finish();
+
+        if id != 0 {
+            return id;
+        }
+    }
+}
@@ -0,0 +1,416 @@
+// Copyright (c) 2019 Jason White
+// Copyright (c) 2019 Mike Lubinets
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use futures::channel::oneshot;
+use futures::future;
+use hyper::{
+    header::{self, HeaderMap},
+    http::StatusCode,
+    server::conn::AddrStream,
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server,
+};
+use serde_json::{json, Value};
+
+use crate::{ClientPool, JWTCredentials};
+
+/// A throwaway RSA key for signing the JWTs sent to the fake API. It is not
+/// used anywhere else and must never be trusted by anything.
+const FAKE_KEY: &[u8] = include_bytes!("fake_key.der");
+
+/// The ID of the fake app.
+pub const FAKE_APP_ID: u64 = 1;
+
+type Handler =
+    Arc<dyn Fn(&ReceivedRequest) -> (StatusCode, Value) + Send + Sync>;
+
+struct Route {
+    method: Method,
+    pattern: String,
+    handler: Handler,
+}
+
+impl Route {
+    /// Checks if the route matches the request. In the pattern, `*` matches a
+    /// single path segment and a trailing `**` matches the rest of the path.
+    fn matches(&self, request: &ReceivedRequest) -> bool {
+        if self.method != request.method {
+            return false;
+        }
+
+        let mut pattern = self.pattern.trim_matches('/').split('/');
+        let mut path = request.path.trim_matches('/').split('/');
+
+        loop {
+            match (pattern.next(), path.next()) {
+                (Some("**"), Some(_)) => return true,
+                (Some("*"), Some(_)) => {}
+                (Some(p), Some(s)) if p == s => {}
+                (None, None) => return true,
+                _ => return false,
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct State {
+    routes: Vec<Route>,
+    requests: Vec<ReceivedRequest>,
+}
+
+/// A request received by [`FakeGithub`].
+#[derive(Debug, Clone)]
+pub struct ReceivedRequest {
+    pub method: Method,
+    pub path: String,
+    pub query: Option<String>,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+}
+
+impl ReceivedRequest {
+    /// Deserializes the JSON body of the request.
+    pub fn json(&self) -> serde_json::Result<Value> {
+        serde_json::from_slice(&self.body)
+    }
+}
+
+/// An in-process HTTP server that emulates the parts of the GitHub API that
+/// apps commonly use, so that apps can be tested without network access.
+///
+/// Out of the box, it hands out installation access tokens and responds to
+/// requests for git references, issues, issue comments and check runs with
+/// plausible responses. Any route can be overridden with [`FakeGithub::route`].
+/// All requests are recorded and can be inspected with
+/// [`FakeGithub::requests`].
+///
+/// The server is shut down when this is dropped.
+///
+/// # Example
+///
+/// ```no_run
+/// # async fn example() {
+/// use github_app::testing::FakeGithub;
+/// use hyper::Method;
+///
+/// let github = FakeGithub::start().unwrap();
+/// let pool = github.client_pool();
+///
+/// // ... run the app with `pool` ...
+///
+/// assert!(github.received(
+///     Method::DELETE,
+///     "/repos/Codertocat/Hello-World/git/refs/heads/changes"
+/// ));
+/// # }
+/// ```
+pub struct FakeGithub {
+    addr: SocketAddr,
+    state: Arc<Mutex<State>>,
+    shutdown: Option<oneshot::Sender<()>>,
+}
+
+impl FakeGithub {
+    /// Starts the server on a random local port.
+    ///
+    /// This must be called from within a Tokio runtime.
+    pub fn start() -> hyper::Result<Self> {
+        let state = Arc::new(Mutex::new(State::default()));
+        let (shutdown, signal) = oneshot::channel::<()>();
+
+        let server_state = state.clone();
+        let new_service = make_service_fn(move |_: &AddrStream| {
+            let state = server_state.clone();
+
+            future::ok::<_, Infallible>(service_fn(move |req| {
+                handle(state.clone(), req)
+            }))
+        });
+
+        let server = Server::try_bind(&SocketAddr::from(([127, 0, 0, 1], 0)))?
+            .serve(new_service);
+
+        let addr = server.local_addr();
+
+        tokio::spawn(server.with_graceful_shutdown(async {
+            signal.await.ok();
+        }));
+
+        let github = FakeGithub {
+            addr,
+            state,
+            shutdown: Some(shutdown),
+        };
+
+        github.default_routes();
+
+        Ok(github)
+    }
+
+    /// The base URL of the API (e.g., `http://127.0.0.1:1234`).
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// Creates a client pool for the fake app that talks to this server.
+    pub fn client_pool(&self) -> ClientPool {
+        let creds = JWTCredentials::new(FAKE_APP_ID, FAKE_KEY.to_vec())
+            .expect("fake key should be valid");
+
+        ClientPool::new(self.url(), creds)
+    }
+
+    /// Responds to requests matching the method and path pattern with the
+    /// given status and JSON body.
+    ///
+    /// In the pattern, `*` matches a single path segment and a trailing `**`
+    /// matches the rest of the path. Routes that were added later take
+    /// precedence, so this can be used to override the default responses.
+    pub fn route(
+        &self,
+        method: Method,
+        pattern: &str,
+        status: StatusCode,
+        body: Value,
+    ) {
+        self.route_with(method, pattern, move |_| (status, body.clone()));
+    }
+
+    /// Like [`FakeGithub::route`], but computes the response from the
+    /// request.
+    pub fn route_with<F>(&self, method: Method, pattern: &str, handler: F)
+    where
+        F: Fn(&ReceivedRequest) -> (StatusCode, Value) + Send + Sync + 'static,
+    {
+        self.state.lock().unwrap().routes.push(Route {
+            method,
+            pattern: pattern.to_string(),
+            handler: Arc::new(handler),
+        });
+    }
+
+    /// Returns all requests received so far, in order.
+    pub fn requests(&self) -> Vec<ReceivedRequest> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    /// Returns `true` if a request with the given method and path was
+    /// received.
+    pub fn received(&self, method: Method, path: &str) -> bool {
+        self.state
+            .lock()
+            .unwrap()
+            .requests
+            .iter()
+            .any(|r| r.method == method && r.path == path)
+    }
+
+    fn default_routes(&self) {
+        let ids = Arc::new(AtomicU64::new(1));
+
+        // Echoes the body of the request with a new ID.
+        let echo = move |status: StatusCode| {
+            let ids = ids.clone();
+
+            move |request: &ReceivedRequest| {
+                let mut body = request.json().unwrap_or_else(|_| json!({}));
+
+                if let Value::Object(fields) = &mut body {
+                    fields.insert(
+                        "id".into(),
+                        json!(ids.fetch_add(1, Ordering::Relaxed)),
+                    );
+                }
+
+                (status, body)
+            }
+        };
+
+        // Authentication.
+        self.route_with(
+            Method::POST,
+            "/app/installations/*/access_tokens",
+            |request| {
+                let installation =
+                    request.path.split('/').nth(3).unwrap_or_default();
+
+                let body = json!({
+                    "token": format!("fake-token-{}", installation),
+                    "expires_at": "2099-01-01T00:00:00Z",
+                });
+
+                (StatusCode::CREATED, body)
+            },
+        );
+        self.route(
+            Method::GET,
+            "/app",
+            StatusCode::OK,
+            json!({
+                "id": FAKE_APP_ID,
+                "slug": "fake-app",
+                "name": "Fake App",
+            }),
+        );
+
+        // References.
+        self.route_with(Method::GET, "/repos/*/*/git/refs/**", |request| {
+            let git_ref =
+                request.path.splitn(6, '/').nth(5).unwrap_or_default();
+
+            let body = json!({
+                "ref": format!("refs/{}", git_ref.trim_start_matches("refs/")),
+                "object": {
+                    "type": "commit",
+                    "sha": "ec26c3e57ca3a959ca5aad62de7213c562f8c821",
+                },
+            });
+
+            (StatusCode::OK, body)
+        });
+        self.route_with(
+            Method::POST,
+            "/repos/*/*/git/refs",
+            echo(StatusCode::CREATED),
+        );
+        self.route(
+            Method::DELETE,
+            "/repos/*/*/git/refs/**",
+            StatusCode::NO_CONTENT,
+            Value::Null,
+        );
+
+        // Issues and pull requests.
+        self.route_with(Method::GET, "/repos/*/*/issues/*", |request| {
+            let number = request
+                .path
+                .rsplit('/')
+                .next()
+                .and_then(|n| n.parse::<u64>().ok());
+
+            (StatusCode::OK, json!({ "number": number, "state": "open" }))
+        });
+        self.route_with(
+            Method::PATCH,
+            "/repos/*/*/issues/*",
+            echo(StatusCode::OK),
+        );
+        self.route(
+            Method::POST,
+            "/repos/*/*/issues/*/labels",
+            StatusCode::OK,
+            json!([]),
+        );
+
+        // Issue comments.
+        self.route(
+            Method::GET,
+            "/repos/*/*/issues/*/comments",
+            StatusCode::OK,
+            json!([]),
+        );
+        self.route_with(
+            Method::POST,
+            "/repos/*/*/issues/*/comments",
+            echo(StatusCode::CREATED),
+        );
+        self.route_with(
+            Method::PATCH,
+            "/repos/*/*/issues/comments/*",
+            echo(StatusCode::OK),
+        );
+        self.route(
+            Method::DELETE,
+            "/repos/*/*/issues/comments/*",
+            StatusCode::NO_CONTENT,
+            Value::Null,
+        );
+
+        // Check runs.
+        self.route_with(
+            Method::POST,
+            "/repos/*/*/check-runs",
+            echo(StatusCode::CREATED),
+        );
+        self.route_with(
+            Method::PATCH,
+            "/repos/*/*/check-runs/*",
+            echo(StatusCode::OK),
+        );
+    }
+}
+
+impl Drop for FakeGithub {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+    }
+}
+
+async fn handle(
+    state: Arc<Mutex<State>>,
+    req: Request<Body>,
+) -> Result<Response<Body>, hyper::Error> {
+    let (parts, body) = req.into_parts();
+    let body = hyper::body::to_bytes(body).await?;
+
+    let request = ReceivedRequest {
+        method: parts.method,
+        path: parts.uri.path().to_string(),
+        query: parts.uri.query().map(String::from),
+        headers: parts.headers,
+        body: body.to_vec(),
+    };
+
+    let handler = {
+        let mut state = state.lock().unwrap();
+        state.requests.push(request.clone());
+        state
+            .routes
+            .iter()
+            .rev()
+            .find(|route| route.matches(&request))
+            .map(|route| route.handler.clone())
+    };
+
+    let (status, body) = match handler {
+        Some(handler) => handler(&request),
+        None => (StatusCode::NOT_FOUND, json!({ "message": "Not Found" })),
+    };
+
+    let body = if body.is_null() {
+        Body::empty()
+    } else {
+        Body::from(body.to_string())
+    };
+
+    Ok(Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(body)
+        .expect("response should be valid"))
+}
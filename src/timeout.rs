@@ -0,0 +1,208 @@
+// Copyright (c) 2019 Jason White
+// Copyright (c) 2019 Mike Lubinets
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//! Limiting how long an app may take to handle a delivery.
+use std::collections::HashMap;
+use std::fmt;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::{Future, FutureExt};
+use humantime::format_duration;
+use hyper::{header::HeaderMap, http::StatusCode};
+
+use crate::{
+    ClientPool, Delivery, Event, EventType, GithubApp, IntoResponse, Layer,
+    Metrics,
+};
+
+/// A layer that cancels the handling of a delivery once it takes too long.
+/// The app's future is dropped and the delivery fails with
+/// [`TimeoutError::TimedOut`], which is answered with
+/// `504 Gateway Timeout`.
+///
+/// [`App::timeout`](crate::App::timeout) and
+/// [`App::event_timeout`](crate::App::event_timeout) configure the instance
+/// of this layer that `App` applies. There, the time starts once the
+/// delivery's turn has come (see [`App::order_by`](crate::App::order_by)).
+#[derive(Debug, Clone, Default)]
+pub struct TimeoutLayer {
+    timeout: Option<Duration>,
+    events: HashMap<EventType, Duration>,
+    metrics: Option<Arc<Metrics>>,
+}
+
+impl TimeoutLayer {
+    /// Applies the same timeout to all events.
+    pub fn new(timeout: Duration) -> Self {
+        TimeoutLayer {
+            timeout: Some(timeout),
+            ..Self::default()
+        }
+    }
+
+    /// Sets the timeout for a specific type of event. This takes precedence
+    /// over the one given to [`TimeoutLayer::new`].
+    pub fn event(mut self, event: EventType, timeout: Duration) -> Self {
+        self.events.insert(event, timeout);
+        self
+    }
+
+    /// Counts the deliveries that timed out.
+    pub fn metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    pub(crate) fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = Some(timeout);
+    }
+
+    pub(crate) fn set_event(&mut self, event: EventType, timeout: Duration) {
+        self.events.insert(event, timeout);
+    }
+
+    /// Returns the timeout for handling the given type of event, if any.
+    fn timeout(&self, event: EventType) -> Option<Duration> {
+        self.events.get(&event).copied().or(self.timeout)
+    }
+}
+
+impl<T> Layer<T> for TimeoutLayer
+where
+    T: GithubApp + Send + 'static,
+{
+    type App = Timeout<T>;
+
+    fn layer(&self, app: T) -> Self::App {
+        Timeout {
+            app,
+            layer: self.clone(),
+        }
+    }
+}
+
+/// An app with timeouts. See [`TimeoutLayer`].
+#[derive(Clone)]
+pub struct Timeout<T> {
+    app: T,
+    layer: TimeoutLayer,
+}
+
+impl<T> GithubApp for Timeout<T>
+where
+    T: GithubApp + Send + 'static,
+{
+    type Error = TimeoutError<T::Error>;
+    type Future = Pin<Box<dyn Future<Output = Result<(), Self::Error>> + Send>>;
+
+    fn secret(&self) -> Option<&str> {
+        self.app.secret()
+    }
+
+    fn client_pool(&self) -> Option<Arc<ClientPool>> {
+        self.app.client_pool()
+    }
+
+    fn call(&mut self, payload: Event) -> Self::Future {
+        Box::pin(self.app.call(payload).map(|r| r.map_err(TimeoutError::App)))
+    }
+
+    fn handle(&mut self, delivery: Delivery, payload: Event) -> Self::Future {
+        let timeout = self.layer.timeout(delivery.event);
+        let metrics = self.layer.metrics.clone();
+        let future = self.app.handle(delivery, payload);
+
+        let timeout = match timeout {
+            Some(timeout) => timeout,
+            None => {
+                return Box::pin(future.map(|r| r.map_err(TimeoutError::App)))
+            }
+        };
+
+        Box::pin(async move {
+            match tokio::time::timeout(timeout, future).await {
+                Ok(result) => result.map_err(TimeoutError::App),
+                Err(_) => {
+                    if let Some(metrics) = metrics {
+                        metrics.inc_timeouts();
+                    }
+
+                    tracing::warn!(
+                        "timed out after {}",
+                        format_duration(timeout)
+                    );
+                    Err(TimeoutError::TimedOut(timeout))
+                }
+            }
+        })
+    }
+}
+
+/// The error of an app with a [`TimeoutLayer`].
+#[derive(Debug)]
+pub enum TimeoutError<E> {
+    /// The app did not finish within the given time.
+    TimedOut(Duration),
+
+    /// The app failed.
+    App(E),
+}
+
+impl<E> fmt::Display for TimeoutError<E>
+where
+    E: fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TimeoutError::TimedOut(timeout) => {
+                write!(f, "Timed out after {}", format_duration(*timeout))
+            }
+            TimeoutError::App(err) => err.fmt(f),
+        }
+    }
+}
+
+impl<E> IntoResponse for TimeoutError<E>
+where
+    E: IntoResponse,
+{
+    fn status(&self) -> StatusCode {
+        match self {
+            TimeoutError::TimedOut(_) => StatusCode::GATEWAY_TIMEOUT,
+            TimeoutError::App(err) => err.status(),
+        }
+    }
+
+    fn body(&self) -> String {
+        match self {
+            TimeoutError::TimedOut(_) => self.to_string(),
+            TimeoutError::App(err) => err.body(),
+        }
+    }
+
+    fn headers(&self) -> HeaderMap {
+        match self {
+            TimeoutError::TimedOut(_) => HeaderMap::new(),
+            TimeoutError::App(err) => err.headers(),
+        }
+    }
+}
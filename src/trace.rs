@@ -0,0 +1,90 @@
+// Copyright (c) 2019 Jason White
+// Copyright (c) 2019 Mike Lubinets
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+use std::fmt;
+
+/// A [W3C trace context](https://www.w3.org/TR/trace-context/) that can be
+/// propagated to outbound API calls via the `traceparent` header.
+///
+/// GitHub does not send a trace context with webhook deliveries, so the trace
+/// ID is derived from the delivery GUID instead. This makes it possible to find
+/// the traces for a delivery using only the ID shown in GitHub's delivery log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceContext {
+    trace_id: u128,
+    parent_id: u64,
+}
+
+impl TraceContext {
+    /// Creates a trace context from a delivery GUID (e.g.,
+    /// `72d3162e-cc78-11e3-81ab-4c9367dc0958`). Returns `None` if the GUID is
+    /// not a valid 128-bit hex string.
+    ///
+    /// The same GUID always results in the same context.
+    pub fn from_delivery_id(id: &str) -> Option<Self> {
+        let hex: String = id.chars().filter(|c| *c != '-').collect();
+
+        if hex.len() != 32 {
+            return None;
+        }
+
+        let trace_id = u128::from_str_radix(&hex, 16).ok()?;
+
+        // An all-zero trace ID is invalid.
+        if trace_id == 0 {
+            return None;
+        }
+
+        // The parent ID is derived from the trace ID as well, so that every
+        // context for the same delivery is equal. This lets `ClientPool`
+        // reuse the HTTP client it created for the delivery.
+        let parent_id = match (trace_id >> 64) as u64 ^ trace_id as u64 {
+            0 => 1,
+            id => id,
+        };
+
+        Some(TraceContext {
+            trace_id,
+            parent_id,
+        })
+    }
+
+    /// The 128-bit trace ID.
+    pub fn trace_id(&self) -> u128 {
+        self.trace_id
+    }
+
+    /// The 64-bit ID of the parent span.
+    pub fn parent_id(&self) -> u64 {
+        self.parent_id
+    }
+
+    /// Returns the value of the `traceparent` header.
+    pub fn traceparent(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl fmt::Display for TraceContext {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Version 00, always sampled.
+        write!(f, "00-{:032x}-{:016x}-01", self.trace_id, self.parent_id)
+    }
+}
@@ -0,0 +1,118 @@
+// Copyright (c) 2019 Jason White
+// Copyright (c) 2019 Mike Lubinets
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+use std::error::Error as StdError;
+use std::pin::Pin;
+
+use futures::future::poll_fn;
+use futures::task::{Context, Poll};
+use futures::{Future, TryFutureExt};
+use hyper::{service::Service, Body, Method, Request, Response};
+
+use crate::{App, GithubApp};
+
+type BoxError = Box<dyn StdError + Send + Sync>;
+
+/// A [tower] layer that sends webhook deliveries to an app and all other
+/// requests to the wrapped service.
+///
+/// This makes it possible to serve webhooks from an existing tower-based HTTP
+/// stack, next to other routes. Deliveries are recognized by their method
+/// (`POST`) and path.
+///
+/// [tower]: https://docs.rs/tower
+#[derive(Clone)]
+pub struct WebhookLayer<T> {
+    app: App<T>,
+    path: String,
+}
+
+impl<T> WebhookLayer<T> {
+    /// Sends deliveries for the given path (e.g., `/github/webhook`) to the
+    /// app.
+    pub fn new<P>(app: App<T>, path: P) -> Self
+    where
+        P: Into<String>,
+    {
+        WebhookLayer {
+            app,
+            path: path.into(),
+        }
+    }
+}
+
+impl<T, S> tower_layer::Layer<S> for WebhookLayer<T>
+where
+    T: Clone,
+{
+    type Service = Webhook<T, S>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        Webhook {
+            app: self.app.clone(),
+            path: self.path.clone(),
+            service,
+        }
+    }
+}
+
+/// The service created by [`WebhookLayer`].
+#[derive(Clone)]
+pub struct Webhook<T, S> {
+    app: App<T>,
+    path: String,
+    service: S,
+}
+
+impl<T, S> Service<Request<Body>> for Webhook<T, S>
+where
+    T: GithubApp + Sync + Send + 'static,
+    S: Service<Request<Body>, Response = Response<Body>>,
+    S::Error: Into<BoxError>,
+    S::Future: Send + 'static,
+{
+    type Response = Response<Body>;
+    type Error = BoxError;
+    type Future = Pin<
+        Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>,
+    >;
+
+    fn poll_ready(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        if req.method() != Method::POST || req.uri().path() != self.path {
+            return Box::pin(self.service.call(req).map_err(Into::into));
+        }
+
+        // Only the wrapped service is polled in `poll_ready`, so the app is
+        // made ready here. This is where its concurrency limit applies.
+        let mut app = self.app.clone();
+
+        Box::pin(async move {
+            poll_fn(|cx| app.poll_ready(cx)).await?;
+            Ok(app.call(req).await?)
+        })
+    }
+}
@@ -0,0 +1,170 @@
+// Copyright (c) 2019 Jason White
+// Copyright (c) 2019 Mike Lubinets
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+#![cfg(feature = "fixtures")]
+
+mod common;
+
+use std::sync::Arc;
+
+use github_app::testing::FakeGithub;
+use github_app::{
+    Annotation, AnnotationLevel, ApiError, CheckOutput, Checks, EventType,
+};
+use hyper::Method;
+use serde_json::Value;
+
+const REPOSITORY: &str = "Codertocat/Hello-World";
+
+/// The bodies of the requests that created check runs.
+fn created(github: &FakeGithub) -> Vec<Value> {
+    github
+        .requests()
+        .iter()
+        .filter(|r| {
+            r.method == Method::POST
+                && r.path == "/repos/Codertocat/Hello-World/check-runs"
+        })
+        .map(|r| r.json().unwrap())
+        .collect()
+}
+
+/// Registers checks that create a check run and nothing else.
+fn checks(github: &FakeGithub, names: &[&str]) -> Checks {
+    let pool = Arc::new(github.client_pool());
+
+    names.iter().fold(Checks::new(), |checks, name| {
+        let pool = pool.clone();
+        checks.check(name, move |ctx| {
+            let pool = pool.clone();
+            async move {
+                ctx.check_run(&pool).create().await?;
+                Ok::<_, ApiError>(())
+            }
+        })
+    })
+}
+
+#[tokio::test]
+async fn annotations_are_sent_in_batches() {
+    let github = FakeGithub::start().unwrap();
+    let pool = github.client_pool();
+
+    let run = pool
+        .check_run(1, REPOSITORY, "lint", "abc123")
+        .create()
+        .await
+        .unwrap();
+
+    let output = CheckOutput::new("Lint", "Lots of problems");
+    let annotations: Vec<_> = (1..=120)
+        .map(|line| {
+            Annotation::new("src/lib.rs", line, AnnotationLevel::Warning, "Hm")
+        })
+        .collect();
+    run.annotate(&output, &annotations).await.unwrap();
+
+    let path = format!("/repos/Codertocat/Hello-World/check-runs/{}", run.id());
+    let batches: Vec<Value> = github
+        .requests()
+        .iter()
+        .filter(|r| r.method == Method::PATCH && r.path == path)
+        .map(|r| r.json().unwrap()["output"].clone())
+        .collect();
+
+    let lines: Vec<Vec<u64>> = batches
+        .iter()
+        .map(|output| {
+            output["annotations"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|a| a["start_line"].as_u64().unwrap())
+                .collect()
+        })
+        .collect();
+    assert_eq!(
+        lines,
+        vec![
+            (1..=50u64).collect::<Vec<_>>(),
+            (51..=100).collect(),
+            (101..=120).collect(),
+        ]
+    );
+
+    // Every batch repeats the output.
+    for output in &batches {
+        assert_eq!(output["title"], "Lint");
+        assert_eq!(output["summary"], "Lots of problems");
+    }
+}
+
+#[tokio::test]
+async fn rerequested_suites_run_every_check() {
+    let github = FakeGithub::start().unwrap();
+    let checks = checks(&github, &["lint", "Octocoders-linter"]);
+
+    let payload = common::payload(EventType::CheckSuite, "rerequested");
+    let (delivery, event) =
+        common::delivery(EventType::CheckSuite, &payload).await;
+
+    assert_eq!(checks.dispatch(&delivery, &event).await.unwrap(), 2);
+
+    let mut names: Vec<_> = created(&github)
+        .iter()
+        .map(|body| body["name"].as_str().unwrap().to_string())
+        .collect();
+    names.sort();
+    assert_eq!(names, ["Octocoders-linter", "lint"]);
+
+    for body in created(&github) {
+        assert_eq!(body["head_sha"], payload["check_suite"]["head_sha"]);
+    }
+}
+
+#[tokio::test]
+async fn rerequested_runs_only_run_their_check() {
+    let github = FakeGithub::start().unwrap();
+    let checks = checks(&github, &["lint", "Octocoders-linter"]);
+
+    let payload = common::payload(EventType::CheckRun, "rerequested");
+    let (delivery, event) =
+        common::delivery(EventType::CheckRun, &payload).await;
+
+    assert_eq!(checks.dispatch(&delivery, &event).await.unwrap(), 1);
+
+    let created = created(&github);
+    assert_eq!(created.len(), 1);
+    assert_eq!(created[0]["name"], payload["check_run"]["name"]);
+    assert_eq!(created[0]["head_sha"], payload["check_run"]["head_sha"]);
+}
+
+#[tokio::test]
+async fn other_actions_run_nothing() {
+    let github = FakeGithub::start().unwrap();
+    let checks = checks(&github, &["lint"]);
+
+    let payload = common::payload(EventType::CheckRun, "completed");
+    let (delivery, event) =
+        common::delivery(EventType::CheckRun, &payload).await;
+
+    assert_eq!(checks.dispatch(&delivery, &event).await.unwrap(), 0);
+    assert!(created(&github).is_empty());
+}
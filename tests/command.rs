@@ -0,0 +1,202 @@
+// Copyright (c) 2019 Jason White
+// Copyright (c) 2019 Mike Lubinets
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+#![cfg(feature = "fixtures")]
+
+mod common;
+
+use github_app::testing::FakeGithub;
+use github_app::{parse_commands, Command, Commands, EventType, Permission};
+use hyper::{http::StatusCode, Method};
+use serde_json::json;
+
+fn command(name: &str, args: &[&str]) -> Command {
+    Command {
+        name: name.to_string(),
+        args: args.iter().map(|arg| arg.to_string()).collect(),
+    }
+}
+
+#[test]
+fn parses_commands_on_their_own_lines() {
+    let body = "Thanks!\n\n/label bug\n  /assign @octocat\nnot /a command";
+
+    assert_eq!(
+        parse_commands(body, None),
+        vec![command("label", &["bug"]), command("assign", &["@octocat"])]
+    );
+}
+
+#[test]
+fn quotes_group_words() {
+    assert_eq!(
+        parse_commands(r#"/label "needs triage" "" bug"#, None),
+        vec![command("label", &["needs triage", "", "bug"])]
+    );
+    assert_eq!(
+        parse_commands(r#"/say he"llo wor"ld"#, None),
+        vec![command("say", &["hello world"])]
+    );
+}
+
+#[test]
+fn ignores_code_blocks_and_quotes() {
+    let body = "```\n/label bug\n```\n> /label wontfix\n/label feature";
+
+    assert_eq!(
+        parse_commands(body, None),
+        vec![command("label", &["feature"])]
+    );
+}
+
+#[test]
+fn parses_bot_mentions() {
+    let bot = Some("my-app[bot]");
+
+    assert_eq!(
+        parse_commands("@my-app[bot] label bug", bot),
+        vec![command("label", &["bug"])]
+    );
+    assert_eq!(
+        parse_commands("@my-app[bot]   /label bug", bot),
+        vec![command("label", &["bug"])]
+    );
+
+    // The mention has to be followed by whitespace and be the bot's login.
+    assert!(parse_commands("@my-app[bot]label bug", bot).is_empty());
+    assert!(parse_commands("@other-app[bot] label bug", bot).is_empty());
+
+    // Without a login, only slash commands are recognized.
+    assert!(parse_commands("@my-app[bot] label bug", None).is_empty());
+}
+
+const REACTIONS: &str =
+    "/repos/Codertocat/Hello-World/issues/comments/492700400/reactions";
+const COMMENTS: &str = "/repos/Codertocat/Hello-World/issues/1/comments";
+
+/// Starts a fake API where Codertocat has the given permission and role.
+fn github(permission: &str, role_name: &str) -> FakeGithub {
+    let github = FakeGithub::start().unwrap();
+
+    github.route(
+        Method::GET,
+        "/repos/Codertocat/Hello-World/collaborators/Codertocat/permission",
+        StatusCode::OK,
+        json!({ "permission": permission, "role_name": role_name }),
+    );
+    github.route(
+        Method::POST,
+        "/repos/*/*/issues/comments/*/reactions",
+        StatusCode::CREATED,
+        json!({}),
+    );
+
+    github
+}
+
+fn commands() -> Commands {
+    Commands::new().command(
+        "label",
+        Permission::Write,
+        "/label <name>",
+        |ctx| async move {
+            let label: String = ctx.command.arg(0)?;
+            Ok(Some(format!("Added `{}`", label)))
+        },
+    )
+}
+
+/// Dispatches a comment written by Codertocat.
+async fn dispatch(github: &FakeGithub, body: &str) -> usize {
+    let mut payload = common::payload(EventType::IssueComment, "created");
+    payload["comment"]["body"] = json!(body);
+
+    let (delivery, event) =
+        common::delivery(EventType::IssueComment, &payload).await;
+
+    commands()
+        .dispatch(&github.client_pool(), &delivery, &event)
+        .await
+        .unwrap()
+}
+
+/// The bodies of the requests made to the given path.
+fn bodies(github: &FakeGithub, path: &str) -> Vec<serde_json::Value> {
+    github
+        .requests()
+        .iter()
+        .filter(|r| r.method == Method::POST && r.path == path)
+        .map(|r| r.json().unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn runs_permitted_commands() {
+    let github = github("write", "write");
+
+    assert_eq!(dispatch(&github, "/label bug\n/unknown").await, 1);
+
+    assert_eq!(bodies(&github, REACTIONS), vec![json!({ "content": "+1" })]);
+    assert_eq!(bodies(&github, COMMENTS)[0]["body"], "Added `bug`");
+}
+
+#[tokio::test]
+async fn reports_usage_errors() {
+    let github = github("admin", "admin");
+
+    assert_eq!(dispatch(&github, "/label").await, 1);
+
+    assert_eq!(
+        bodies(&github, REACTIONS),
+        vec![json!({ "content": "confused" })]
+    );
+    assert_eq!(
+        bodies(&github, COMMENTS)[0]["body"],
+        "Missing argument 1\n\nUsage: `/label <name>`"
+    );
+}
+
+#[tokio::test]
+async fn refuses_commands_without_permission() {
+    let github = github("read", "triage");
+
+    assert_eq!(dispatch(&github, "/label bug").await, 0);
+
+    assert_eq!(bodies(&github, REACTIONS), vec![json!({ "content": "-1" })]);
+    assert_eq!(
+        bodies(&github, COMMENTS)[0]["body"],
+        "@Codertocat does not have permission to run `/label`"
+    );
+}
+
+#[tokio::test]
+async fn custom_roles_fall_back_to_base_permission() {
+    let github = github("write", "release-manager");
+
+    assert_eq!(dispatch(&github, "/label bug").await, 1);
+}
+
+#[tokio::test]
+async fn ignores_comments_without_commands() {
+    let github = github("write", "write");
+
+    assert_eq!(dispatch(&github, "Looks good to me").await, 0);
+    assert!(github.requests().is_empty());
+}
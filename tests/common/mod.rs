@@ -0,0 +1,105 @@
+// Copyright (c) 2019 Jason White
+// Copyright (c) 2019 Mike Lubinets
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//! Helpers shared by the integration tests.
+#![allow(dead_code)]
+
+use std::convert::Infallible;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use futures::{future, Future};
+use github_app::testing::TestRequest;
+use github_app::{
+    fixtures, parse_event, App, Delivery, Event, EventType, GithubApp,
+};
+use serde_json::Value;
+use tokio::sync::Semaphore;
+
+/// An app that does nothing.
+#[derive(Clone)]
+pub struct Noop;
+
+impl GithubApp for Noop {
+    type Error = Infallible;
+    type Future = Pin<Box<dyn Future<Output = Result<(), Self::Error>> + Send>>;
+
+    fn call(&mut self, _event: Event) -> Self::Future {
+        Box::pin(future::ok(()))
+    }
+}
+
+/// The JSON payload of a fixture.
+pub fn payload(event: EventType, name: &str) -> Value {
+    let fixture = fixtures::get(event, name).unwrap();
+    serde_json::from_str(fixture.payload).unwrap()
+}
+
+/// Sends a payload through an app to get the delivery metadata for it, along
+/// with the parsed event.
+pub async fn delivery(event: EventType, payload: &Value) -> (Delivery, Event) {
+    let body = payload.to_string();
+    let response = TestRequest::new(event, body.clone())
+        .send(&mut App::new(Noop))
+        .await
+        .unwrap();
+
+    let delivery = response.extensions().get::<Delivery>().unwrap().clone();
+    (delivery, parse_event(event, body.as_bytes()).unwrap())
+}
+
+/// An app that signals when it starts handling a delivery and then waits
+/// until it is released.
+#[derive(Clone)]
+pub struct Gated {
+    pub started: Arc<Semaphore>,
+    pub release: Arc<Semaphore>,
+}
+
+impl Default for Gated {
+    fn default() -> Self {
+        Gated {
+            started: Arc::new(Semaphore::new(0)),
+            release: Arc::new(Semaphore::new(0)),
+        }
+    }
+}
+
+impl Gated {
+    pub async fn wait_started(&self) {
+        self.started.acquire().await.forget();
+    }
+}
+
+impl GithubApp for Gated {
+    type Error = Infallible;
+    type Future = Pin<Box<dyn Future<Output = Result<(), Self::Error>> + Send>>;
+
+    fn call(&mut self, _event: Event) -> Self::Future {
+        let started = self.started.clone();
+        let release = self.release.clone();
+
+        Box::pin(async move {
+            started.add_permits(1);
+            release.acquire().await.forget();
+            Ok(())
+        })
+    }
+}
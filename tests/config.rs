@@ -0,0 +1,231 @@
+// Copyright (c) 2019 Jason White
+// Copyright (c) 2019 Mike Lubinets
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+#![cfg(feature = "fixtures")]
+
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use github_app::testing::FakeGithub;
+use github_app::{ConfigError, ConfigLoader, EventType};
+use hyper::{http::StatusCode, Method};
+use serde::Deserialize;
+use serde_json::json;
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Config {
+    label: String,
+}
+
+const REPO_FILE: &str =
+    "/repos/Codertocat/Hello-World/contents/.github/app.yml";
+const ORG_FILE: &str = "/repos/Codertocat/.github/contents/.github/app.yml";
+
+/// Serves a file with the given blob SHA and contents.
+fn serve(github: &FakeGithub, path: &str, sha: &str, contents: &str) {
+    github.route(
+        Method::GET,
+        path,
+        StatusCode::OK,
+        json!({
+            "sha": sha,
+            "encoding": "base64",
+            "content": base64::encode(contents),
+        }),
+    );
+}
+
+fn fetches(github: &FakeGithub, path: &str) -> usize {
+    github
+        .requests()
+        .iter()
+        .filter(|r| r.method == Method::GET && r.path == path)
+        .count()
+}
+
+async fn load(
+    github: &FakeGithub,
+    loader: &ConfigLoader<Config>,
+) -> Option<Arc<Config>> {
+    loader
+        .load_for(&github.client_pool(), 1, "Codertocat/Hello-World")
+        .await
+        .unwrap()
+}
+
+fn config(label: &str) -> Option<Config> {
+    Some(Config {
+        label: label.to_string(),
+    })
+}
+
+/// Tells the loader about a push to the repository that changed the files.
+async fn push(loader: &ConfigLoader<Config>, repository: &str, files: &[&str]) {
+    let mut payload = common::payload(EventType::Push, "branch");
+    payload["repository"]["full_name"] = json!(repository);
+    payload["commits"] =
+        json!([{ "added": [], "removed": [], "modified": files }]);
+
+    let (delivery, _) = common::delivery(EventType::Push, &payload).await;
+    loader.observe(&delivery);
+}
+
+#[tokio::test]
+async fn loads_and_caches_config() {
+    let github = FakeGithub::start().unwrap();
+    serve(&github, REPO_FILE, "a", "label: bug\n");
+
+    let loader = ConfigLoader::<Config>::new("app.yml");
+
+    assert_eq!(
+        load(&github, &loader).await.as_deref(),
+        config("bug").as_ref()
+    );
+    assert_eq!(
+        load(&github, &loader).await.as_deref(),
+        config("bug").as_ref()
+    );
+    assert_eq!(fetches(&github, REPO_FILE), 1);
+}
+
+#[tokio::test]
+async fn falls_back_to_org_config() {
+    let github = FakeGithub::start().unwrap();
+    serve(&github, ORG_FILE, "a", "label: org\n");
+
+    let loader = ConfigLoader::<Config>::new("app.yml");
+    assert_eq!(
+        load(&github, &loader).await.as_deref(),
+        config("org").as_ref()
+    );
+
+    let loader = ConfigLoader::<Config>::new("app.yml").org_fallback(false);
+    assert_eq!(load(&github, &loader).await, None);
+}
+
+#[tokio::test]
+async fn missing_config_is_none() {
+    let github = FakeGithub::start().unwrap();
+    let loader = ConfigLoader::<Config>::new("app.yml");
+
+    assert_eq!(load(&github, &loader).await, None);
+    assert_eq!(fetches(&github, REPO_FILE), 1);
+    assert_eq!(fetches(&github, ORG_FILE), 1);
+}
+
+#[tokio::test]
+async fn invalid_config_is_an_error() {
+    let github = FakeGithub::start().unwrap();
+    serve(&github, REPO_FILE, "a", "label: [bug\n");
+
+    let loader = ConfigLoader::<Config>::new("app.yml");
+    let result = loader
+        .load_for(&github.client_pool(), 1, "Codertocat/Hello-World")
+        .await;
+
+    match result {
+        Err(ConfigError::Invalid { repository, .. }) => {
+            assert_eq!(repository, "Codertocat/Hello-World")
+        }
+        other => panic!("unexpected result: {:?}", other.map(|_| ())),
+    }
+}
+
+#[tokio::test]
+async fn pushes_that_change_the_file_invalidate() {
+    let github = FakeGithub::start().unwrap();
+    serve(&github, REPO_FILE, "a", "label: bug\n");
+
+    let loader = ConfigLoader::<Config>::new("app.yml");
+    load(&github, &loader).await;
+
+    serve(&github, REPO_FILE, "b", "label: feature\n");
+
+    push(&loader, "Codertocat/Hello-World", &["README.md"]).await;
+    assert_eq!(
+        load(&github, &loader).await.as_deref(),
+        config("bug").as_ref()
+    );
+
+    push(&loader, "Codertocat/Hello-World", &[".github/app.yml"]).await;
+    assert_eq!(
+        load(&github, &loader).await.as_deref(),
+        config("feature").as_ref()
+    );
+}
+
+#[tokio::test]
+async fn pushes_without_files_invalidate() {
+    let github = FakeGithub::start().unwrap();
+    serve(&github, REPO_FILE, "a", "label: bug\n");
+
+    let loader = ConfigLoader::<Config>::new("app.yml");
+    load(&github, &loader).await;
+
+    serve(&github, REPO_FILE, "b", "label: feature\n");
+
+    // A force push lists no commits.
+    push(&loader, "Codertocat/Hello-World", &[]).await;
+    assert_eq!(
+        load(&github, &loader).await.as_deref(),
+        config("feature").as_ref()
+    );
+}
+
+#[tokio::test]
+async fn org_pushes_invalidate_dependents() {
+    let github = FakeGithub::start().unwrap();
+    serve(&github, ORG_FILE, "a", "label: org\n");
+
+    let loader = ConfigLoader::<Config>::new("app.yml");
+    load(&github, &loader).await;
+
+    serve(&github, ORG_FILE, "b", "label: shared\n");
+
+    push(&loader, "Codertocat/.github", &[".github/app.yml"]).await;
+    assert_eq!(
+        load(&github, &loader).await.as_deref(),
+        config("shared").as_ref()
+    );
+}
+
+#[tokio::test]
+async fn expired_files_are_revalidated() {
+    let github = FakeGithub::start().unwrap();
+    serve(&github, REPO_FILE, "a", "label: bug\n");
+
+    let loader =
+        ConfigLoader::<Config>::new("app.yml").ttl(Duration::from_secs(0));
+    let first = load(&github, &loader).await.unwrap();
+
+    // The blob is the same, so the parsed value is reused.
+    let second = load(&github, &loader).await.unwrap();
+    assert!(Arc::ptr_eq(&first, &second));
+    assert_eq!(fetches(&github, REPO_FILE), 2);
+
+    // A missed push is picked up once the file is fetched again.
+    serve(&github, REPO_FILE, "b", "label: feature\n");
+    assert_eq!(
+        load(&github, &loader).await.as_deref(),
+        config("feature").as_ref()
+    );
+}
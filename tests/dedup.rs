@@ -0,0 +1,136 @@
+// Copyright (c) 2019 Jason White
+// Copyright (c) 2019 Mike Lubinets
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+#![cfg(feature = "fixtures")]
+
+mod common;
+
+use std::convert::Infallible;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::future::{self, poll_fn};
+use futures::Future;
+use github_app::{
+    fixtures, App, DedupLayer, Event, EventType, GithubApp, GithubAppExt,
+};
+use hyper::{header::HeaderMap, service::Service, Body, Request};
+
+use common::Gated;
+
+/// A delivery that can be sent more than once, with the same ID.
+struct Redeliverable {
+    headers: HeaderMap,
+    body: Vec<u8>,
+}
+
+impl Redeliverable {
+    fn new() -> Self {
+        let request = fixtures::get(EventType::Issues, "opened")
+            .unwrap()
+            .request();
+        let body = request.body().to_vec();
+
+        Redeliverable {
+            headers: request.into_request().headers().clone(),
+            body,
+        }
+    }
+
+    async fn send<T>(&self, app: &App<T>) -> u16
+    where
+        T: GithubApp + Sync + Send + 'static,
+    {
+        let mut request = Request::post("/")
+            .body(Body::from(self.body.clone()))
+            .unwrap();
+        *request.headers_mut() = self.headers.clone();
+
+        let mut app = app.clone();
+        poll_fn(|cx| app.poll_ready(cx)).await.unwrap();
+        app.call(request).await.unwrap().status().as_u16()
+    }
+}
+
+/// An app that panics the first time it is called.
+#[derive(Clone, Default)]
+struct PanicsOnce {
+    calls: Arc<AtomicUsize>,
+}
+
+impl GithubApp for PanicsOnce {
+    type Error = Infallible;
+    type Future = Pin<Box<dyn Future<Output = Result<(), Self::Error>> + Send>>;
+
+    fn call(&mut self, _event: Event) -> Self::Future {
+        if self.calls.fetch_add(1, Ordering::SeqCst) == 0 {
+            panic!("oh no");
+        }
+
+        Box::pin(future::ok(()))
+    }
+}
+
+#[tokio::test]
+async fn duplicates_are_ignored() {
+    let gated = Gated::default();
+    gated.release.add_permits(2);
+    let app = App::new(gated.clone().layer(DedupLayer::new(16)));
+
+    let delivery = Redeliverable::new();
+    assert_eq!(delivery.send(&app).await, 200);
+    assert_eq!(delivery.send(&app).await, 200);
+
+    assert_eq!(gated.started.available_permits(), 1);
+}
+
+#[tokio::test]
+async fn timed_out_deliveries_are_handled_again() {
+    let gated = Gated::default();
+    let app = App::new(gated.clone().layer(DedupLayer::new(16)))
+        .timeout(Duration::from_millis(50));
+
+    let delivery = Redeliverable::new();
+    assert_eq!(delivery.send(&app).await, 504);
+
+    gated.release.add_permits(1);
+    assert_eq!(delivery.send(&app).await, 200);
+    assert_eq!(gated.started.available_permits(), 2);
+
+    // Once it has succeeded, it is a duplicate.
+    assert_eq!(delivery.send(&app).await, 200);
+    assert_eq!(gated.started.available_permits(), 2);
+}
+
+#[tokio::test]
+async fn panicked_deliveries_are_handled_again() {
+    let app = PanicsOnce::default();
+    let calls = app.calls.clone();
+    let app = App::new(app.layer(DedupLayer::new(16)));
+
+    let delivery = Redeliverable::new();
+    assert_eq!(delivery.send(&app).await, 500);
+    assert_eq!(delivery.send(&app).await, 200);
+    assert_eq!(delivery.send(&app).await, 200);
+
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+}
@@ -0,0 +1,80 @@
+// Copyright (c) 2019 Jason White
+// Copyright (c) 2019 Mike Lubinets
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+#![cfg(feature = "fixtures")]
+
+mod common;
+
+use github_app::{filter, EventType, Filter};
+use serde_json::json;
+
+/// Returns `true` if a push to the branch matches the pattern.
+async fn push_matches(pattern: &str, branch: &str) -> bool {
+    let mut payload = common::payload(EventType::Push, "branch");
+    payload["ref"] = json!(format!("refs/heads/{}", branch));
+
+    let (delivery, event) = common::delivery(EventType::Push, &payload).await;
+    assert_eq!(delivery.branch.as_deref(), Some(branch));
+
+    filter::branch(pattern).matches(&delivery, &event)
+}
+
+#[tokio::test]
+async fn literal_patterns_match_exactly() {
+    assert!(push_matches("master", "master").await);
+    assert!(!push_matches("master", "master2").await);
+    assert!(!push_matches("master", "old/master").await);
+}
+
+#[tokio::test]
+async fn single_star_stays_within_a_segment() {
+    assert!(push_matches("release/*", "release/1.0").await);
+    assert!(push_matches("release/*", "release/").await);
+    assert!(!push_matches("release/*", "release/1.0/hotfix").await);
+    assert!(!push_matches("release/*", "releases/1.0").await);
+
+    assert!(push_matches("*-fix", "typo-fix").await);
+    assert!(!push_matches("*-fix", "user/typo-fix").await);
+
+    assert!(push_matches("feature/*/wip", "feature/login/wip").await);
+    assert!(!push_matches("feature/*/wip", "feature/a/b/wip").await);
+}
+
+#[tokio::test]
+async fn double_star_crosses_segments() {
+    assert!(push_matches("release/**", "release/1.0").await);
+    assert!(push_matches("release/**", "release/1.0/hotfix").await);
+    assert!(!push_matches("release/**", "releases/1.0").await);
+
+    assert!(push_matches("**/wip", "user/feature/wip").await);
+    assert!(push_matches("**", "any/branch").await);
+
+    assert!(push_matches("feature/**/wip", "feature/a/b/wip").await);
+    assert!(!push_matches("feature/**/wip", "feature/a/b/done").await);
+}
+
+#[tokio::test]
+async fn events_without_a_branch_pass() {
+    let payload = common::payload(EventType::Issues, "opened");
+    let (delivery, event) = common::delivery(EventType::Issues, &payload).await;
+
+    assert_eq!(delivery.branch, None);
+    assert!(filter::branch("release/*").matches(&delivery, &event));
+}
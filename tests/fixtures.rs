@@ -0,0 +1,111 @@
+// Copyright (c) 2019 Jason White
+// Copyright (c) 2019 Mike Lubinets
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+#![cfg(feature = "fixtures")]
+
+use std::convert::Infallible;
+use std::pin::Pin;
+
+use futures::{future, Future};
+use github_app::{fixtures, App, Delivery, Event, EventType, GithubApp};
+
+/// All event types that `parse_event` supports.
+const EVENT_TYPES: &[EventType] = &[
+    EventType::Ping,
+    EventType::CheckRun,
+    EventType::CheckSuite,
+    EventType::CommitComment,
+    EventType::Create,
+    EventType::Delete,
+    EventType::GitHubAppAuthorization,
+    EventType::Gollum,
+    EventType::Installation,
+    EventType::InstallationRepositories,
+    EventType::IntegrationInstallation,
+    EventType::IntegrationInstallationRepositories,
+    EventType::IssueComment,
+    EventType::Issues,
+    EventType::Label,
+    EventType::PullRequest,
+    EventType::PullRequestReview,
+    EventType::PullRequestReviewComment,
+    EventType::Push,
+    EventType::Repository,
+    EventType::Watch,
+];
+
+#[derive(Clone)]
+struct Noop;
+
+impl GithubApp for Noop {
+    type Error = Infallible;
+    type Future = Pin<Box<dyn Future<Output = Result<(), Self::Error>> + Send>>;
+
+    fn secret(&self) -> Option<&str> {
+        Some("fixture secret")
+    }
+
+    fn call(&mut self, _event: Event) -> Self::Future {
+        Box::pin(future::ok(()))
+    }
+}
+
+#[test]
+fn every_event_type_has_a_fixture() {
+    for event in EVENT_TYPES {
+        assert!(
+            fixtures::for_event(*event).next().is_some(),
+            "no fixture for {}",
+            event
+        );
+    }
+}
+
+#[test]
+fn fixtures_parse() {
+    for fixture in fixtures::all() {
+        if let Err(err) = fixture.parse() {
+            panic!(
+                "failed to parse {}/{}: {}",
+                fixture.event, fixture.name, err
+            );
+        }
+    }
+}
+
+#[tokio::test]
+async fn fixtures_round_trip() {
+    let mut app = App::new(Noop);
+
+    for fixture in fixtures::all() {
+        let response = fixture.request().send(&mut app).await.unwrap();
+
+        assert_eq!(
+            response.status(),
+            200,
+            "unexpected response for {}/{}",
+            fixture.event,
+            fixture.name
+        );
+
+        let delivery = response.extensions().get::<Delivery>().unwrap();
+        assert_eq!(delivery.event, fixture.event);
+    }
+}
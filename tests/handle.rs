@@ -0,0 +1,103 @@
+// Copyright (c) 2019 Jason White
+// Copyright (c) 2019 Mike Lubinets
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+#![cfg(feature = "fixtures")]
+
+use std::convert::Infallible;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+
+use futures::{future, Future};
+use github_app::{fixtures, App, Delivery, Event, EventType, GithubApp};
+
+/// An app that records the deliveries it handles.
+#[derive(Clone, Default)]
+struct Recording(Arc<Mutex<Vec<Delivery>>>);
+
+impl GithubApp for Recording {
+    type Error = Infallible;
+    type Future = Pin<Box<dyn Future<Output = Result<(), Self::Error>> + Send>>;
+
+    fn call(&mut self, _event: Event) -> Self::Future {
+        panic!("`call` is not used when `handle` is implemented");
+    }
+
+    fn handle(&mut self, delivery: Delivery, _event: Event) -> Self::Future {
+        self.0.lock().unwrap().push(delivery);
+        Box::pin(future::ok(()))
+    }
+}
+
+#[tokio::test]
+async fn handle_gets_the_delivery() {
+    let app = Recording::default();
+    let fixture = fixtures::get(EventType::Issues, "opened").unwrap();
+
+    fixture
+        .request()
+        .id("72d3162e-cc78-11e3-81ab-4c9367dc0958")
+        .send(&mut App::new(app.clone()))
+        .await
+        .unwrap();
+
+    let handled = app.0.lock().unwrap();
+    assert_eq!(handled.len(), 1);
+    assert_eq!(
+        handled[0].id.as_deref(),
+        Some("72d3162e-cc78-11e3-81ab-4c9367dc0958")
+    );
+    assert_eq!(handled[0].event, EventType::Issues);
+    assert_eq!(handled[0].action.as_deref(), Some("opened"));
+    assert_eq!(handled[0].installation, Some(2311213));
+    assert_eq!(
+        handled[0].repository.as_deref(),
+        Some("Codertocat/Hello-World")
+    );
+    assert_eq!(handled[0].number, Some(1));
+}
+
+#[tokio::test]
+async fn handle_calls_call_by_default() {
+    /// An app that only implements `call`.
+    #[derive(Clone, Default)]
+    struct CallOnly(Arc<Mutex<usize>>);
+
+    impl GithubApp for CallOnly {
+        type Error = Infallible;
+        type Future =
+            Pin<Box<dyn Future<Output = Result<(), Self::Error>> + Send>>;
+
+        fn call(&mut self, _event: Event) -> Self::Future {
+            *self.0.lock().unwrap() += 1;
+            Box::pin(future::ok(()))
+        }
+    }
+
+    let app = CallOnly::default();
+    let fixture = fixtures::get(EventType::Issues, "opened").unwrap();
+
+    fixture
+        .request()
+        .send(&mut App::new(app.clone()))
+        .await
+        .unwrap();
+
+    assert_eq!(*app.0.lock().unwrap(), 1);
+}
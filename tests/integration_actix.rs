@@ -0,0 +1,103 @@
+// Copyright (c) 2019 Jason White
+// Copyright (c) 2019 Mike Lubinets
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+#![cfg(all(feature = "actix-web", feature = "fixtures"))]
+
+mod common;
+
+use actix_web::{test, web};
+use github_app::integration::actix::{handler, WebhookConfig, WebhookEvent};
+use github_app::testing::TestRequest;
+use github_app::{fixtures, App, EventType};
+
+use common::Noop;
+
+/// Turns a test request into an actix request for `/webhook`.
+fn webhook_request(request: TestRequest) -> test::TestRequest {
+    let body = request.body().to_vec();
+    let request = request.into_request();
+
+    request.headers().iter().fold(
+        test::TestRequest::post().uri("/webhook").set_payload(body),
+        |builder, (name, value)| {
+            builder.header(name.as_str(), value.to_str().unwrap())
+        },
+    )
+}
+
+#[actix_rt::test]
+async fn handler_passes_deliveries_to_the_app() {
+    let mut service = test::init_service(
+        actix_web::App::new()
+            .route("/webhook", web::post().to(handler(App::new(Noop)))),
+    )
+    .await;
+    let fixture = fixtures::get(EventType::Issues, "opened").unwrap();
+
+    let request = webhook_request(fixture.request()).to_request();
+    let response = test::call_service(&mut service, request).await;
+
+    assert_eq!(response.status(), 200);
+}
+
+#[actix_rt::test]
+async fn handler_replies_with_app_errors() {
+    let mut service = test::init_service(
+        actix_web::App::new()
+            .route("/webhook", web::post().to(handler(App::new(Noop)))),
+    )
+    .await;
+    let fixture = fixtures::get(EventType::Issues, "opened").unwrap();
+
+    // The event doesn't match the payload.
+    let request = webhook_request(fixture.request())
+        .header("X-GitHub-Event", "push")
+        .to_request();
+    let response = test::call_service(&mut service, request).await;
+
+    assert_eq!(response.status(), 400);
+}
+
+#[actix_rt::test]
+async fn extractor_verifies_deliveries() {
+    let mut service = test::init_service(
+        actix_web::App::new()
+            .app_data(WebhookConfig::default().secret("secret"))
+            .route(
+                "/webhook",
+                web::post().to(|webhook: WebhookEvent| async move {
+                    webhook.delivery.event.to_string()
+                }),
+            ),
+    )
+    .await;
+    let fixture = fixtures::get(EventType::Issues, "opened").unwrap();
+
+    let request =
+        webhook_request(fixture.request().secret("secret")).to_request();
+    let response = test::call_service(&mut service, request).await;
+    assert_eq!(response.status(), 200);
+    assert_eq!(test::read_body(response).await, "issues");
+
+    let request =
+        webhook_request(fixture.request().secret("wrong")).to_request();
+    let response = test::call_service(&mut service, request).await;
+    assert_eq!(response.status(), 400);
+}
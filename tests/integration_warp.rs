@@ -0,0 +1,90 @@
+// Copyright (c) 2019 Jason White
+// Copyright (c) 2019 Mike Lubinets
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+#![cfg(all(feature = "warp", feature = "fixtures"))]
+
+mod common;
+
+use github_app::integration::warp::{event, recover, webhook};
+use github_app::testing::TestRequest;
+use github_app::{fixtures, App, Delivery, Event, EventType};
+use warp::{test::RequestBuilder, Filter};
+
+use common::Noop;
+
+/// Turns a test request into a warp request for `/webhook`.
+fn webhook_request(request: TestRequest) -> RequestBuilder {
+    let body = request.body().to_vec();
+    let request = request.into_request();
+
+    request.headers().iter().fold(
+        warp::test::request()
+            .method("POST")
+            .path("/webhook")
+            .body(body),
+        |builder, (name, value)| {
+            builder.header(name.as_str(), value.to_str().unwrap())
+        },
+    )
+}
+
+#[tokio::test]
+async fn webhook_passes_deliveries_to_the_app() {
+    let filter = warp::path("webhook").and(webhook(App::new(Noop)));
+    let fixture = fixtures::get(EventType::Issues, "opened").unwrap();
+
+    let response = webhook_request(fixture.request()).reply(&filter).await;
+
+    assert_eq!(response.status(), 200);
+}
+
+#[tokio::test]
+async fn webhook_replies_with_app_errors() {
+    let filter = warp::path("webhook").and(webhook(App::new(Noop)));
+    let fixture = fixtures::get(EventType::Issues, "opened").unwrap();
+
+    // The event doesn't match the payload.
+    let response = webhook_request(fixture.request())
+        .header("X-GitHub-Event", "push")
+        .reply(&filter)
+        .await;
+
+    assert_eq!(response.status(), 400);
+}
+
+#[tokio::test]
+async fn event_extracts_verified_deliveries() {
+    let filter = warp::path("webhook")
+        .and(event(Some("secret".into())))
+        .map(|delivery: Delivery, _event: Event| delivery.event.to_string())
+        .recover(recover);
+    let fixture = fixtures::get(EventType::Issues, "opened").unwrap();
+
+    let response = webhook_request(fixture.request().secret("secret"))
+        .reply(&filter)
+        .await;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.body().as_ref(), b"issues");
+
+    let response = webhook_request(fixture.request().secret("wrong"))
+        .reply(&filter)
+        .await;
+    assert_eq!(response.status(), 400);
+}
@@ -0,0 +1,114 @@
+// Copyright (c) 2019 Jason White
+// Copyright (c) 2019 Mike Lubinets
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+#![cfg(feature = "fixtures")]
+
+mod common;
+
+use std::time::Duration;
+
+use futures::future::poll_fn;
+use github_app::{fixtures, App, EventType, Overflow};
+use hyper::service::Service;
+
+use common::Gated;
+
+fn reject() -> Overflow {
+    Overflow::Reject {
+        retry_after: Duration::from_secs(1),
+    }
+}
+
+#[tokio::test]
+async fn global_limit_rejects_only_while_full() {
+    let gated = Gated::default();
+    let app = App::new(gated.clone())
+        .concurrency_limit(1)
+        .overflow(reject());
+    let fixture = fixtures::get(EventType::Issues, "opened").unwrap();
+
+    let mut first = app.clone();
+    let handle = tokio::spawn(async move {
+        fixture.request().send(&mut first).await.unwrap().status()
+    });
+    gated.wait_started().await;
+
+    let mut second = app.clone();
+    let response = fixture.request().send(&mut second).await.unwrap();
+    assert_eq!(response.status(), 503);
+    assert_eq!(response.headers()["retry-after"], "1");
+
+    gated.release.add_permits(1);
+    assert_eq!(handle.await.unwrap(), 200);
+
+    // The same clone must not remember that it was full before.
+    gated.release.add_permits(1);
+    let response = fixture.request().send(&mut second).await.unwrap();
+    assert_eq!(response.status(), 200);
+}
+
+#[tokio::test]
+async fn idle_clones_do_not_hold_capacity() {
+    let gated = Gated::default();
+    gated.release.add_permits(1);
+
+    let app = App::new(gated).concurrency_limit(1);
+    let fixture = fixtures::get(EventType::Issues, "opened").unwrap();
+
+    // This is what hyper does with every connection before it reads the
+    // next request.
+    let mut idle = app.clone();
+    poll_fn(|cx| idle.poll_ready(cx)).await.unwrap();
+
+    let mut active = app.clone();
+    let response = tokio::time::timeout(
+        Duration::from_secs(5),
+        fixture.request().send(&mut active),
+    )
+    .await
+    .expect("delivery was blocked by an idle connection")
+    .unwrap();
+
+    assert_eq!(response.status(), 200);
+}
+
+#[tokio::test]
+async fn installation_limit_rejects_same_installation() {
+    let gated = Gated::default();
+    let app = App::new(gated.clone())
+        .installation_concurrency_limit(1)
+        .overflow(reject());
+    let issue = fixtures::get(EventType::Issues, "opened").unwrap();
+    let comment = fixtures::get(EventType::IssueComment, "created").unwrap();
+
+    let mut first = app.clone();
+    let handle = tokio::spawn(async move {
+        issue.request().send(&mut first).await.unwrap().status()
+    });
+    gated.wait_started().await;
+
+    // Both fixtures belong to the same installation.
+    let mut second = app.clone();
+    let response = comment.request().send(&mut second).await.unwrap();
+    assert_eq!(response.status(), 503);
+
+    gated.release.add_permits(1);
+    assert_eq!(handle.await.unwrap(), 200);
+}
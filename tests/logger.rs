@@ -0,0 +1,225 @@
+// Copyright (c) 2019 Jason White
+// Copyright (c) 2019 Mike Lubinets
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+#![cfg(feature = "fixtures")]
+
+mod common;
+
+use std::sync::{Mutex, Once};
+use std::time::Duration;
+
+use futures::future::poll_fn;
+use github_app::testing::TestRequest;
+use github_app::{
+    fixtures, App, DeliveryLoggerLayer, EventType, GithubAppExt, LogConfig,
+    LogFields, LogFormat, Logger, TimeoutLayer,
+};
+use hyper::{service::Service, Body, Request};
+use log::{LevelFilter, Log, Metadata, Record};
+use serde_json::Value;
+
+/// Collects the records logged by the `Logger` and `DeliveryLogger`.
+struct Capture;
+
+static RECORDS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+impl Log for Capture {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.target() == "github_app::logger"
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            RECORDS.lock().unwrap().push(record.args().to_string());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// Sends the request to the path through a `Logger` and returns the record
+/// that was logged for it.
+async fn log(
+    config: LogConfig,
+    path: &str,
+    mut request: Request<Body>,
+) -> String {
+    capture();
+
+    *request.uri_mut() = path.parse().unwrap();
+
+    let mut logger = Logger::with_config(
+        ([127, 0, 0, 1], 1234).into(),
+        App::new(common::Noop),
+        config,
+    );
+    poll_fn(|cx| logger.poll_ready(cx)).await.unwrap();
+    logger.call(request).await.unwrap();
+
+    find(path)
+}
+
+fn capture() {
+    static INIT: Once = Once::new();
+    INIT.call_once(|| {
+        log::set_logger(&Capture).unwrap();
+        log::set_max_level(LevelFilter::Info);
+    });
+}
+
+/// Returns the record that contains the given string.
+fn find(needle: &str) -> String {
+    RECORDS
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|record| record.contains(needle))
+        .cloned()
+        .expect("nothing was logged")
+}
+
+fn opened() -> Request<Body> {
+    let fixture = fixtures::get(EventType::Issues, "opened").unwrap();
+    fixture.request().id("delivery-1").into_request()
+}
+
+#[tokio::test]
+async fn json_records_include_delivery() {
+    let record = log(LogConfig::json(), "/delivery", opened()).await;
+    let record: Value = serde_json::from_str(&record).unwrap();
+
+    assert_eq!(record["remote_addr"], "127.0.0.1");
+    assert_eq!(record["method"], "POST");
+    assert_eq!(record["uri"], "/delivery");
+    assert_eq!(record["delivery"], "delivery-1");
+    assert_eq!(record["event"], "issues");
+    assert_eq!(record["action"], "opened");
+    assert_eq!(record["installation"], 2311213);
+    assert_eq!(record["repository"], "Codertocat/Hello-World");
+    assert_eq!(record["status"], 200);
+    assert!(record["duration_ms"].is_number());
+    assert!(record.get("error").is_none());
+}
+
+#[tokio::test]
+async fn json_records_include_errors() {
+    let request = TestRequest::new(EventType::Issues, "not json")
+        .id("delivery-2")
+        .into_request();
+
+    let record = log(LogConfig::json(), "/error", request).await;
+    let record: Value = serde_json::from_str(&record).unwrap();
+
+    // Without a payload, the delivery ID and event come from the headers.
+    assert_eq!(record["delivery"], "delivery-2");
+    assert_eq!(record["event"], "issues");
+    assert_eq!(record["status"], 400);
+    assert!(record["error"].is_string());
+    assert!(record.get("action").is_none());
+    assert!(record.get("repository").is_none());
+}
+
+#[tokio::test]
+async fn json_fields_can_be_disabled() {
+    let config = LogConfig {
+        format: LogFormat::Json,
+        fields: LogFields {
+            remote_addr: false,
+            uri: false,
+            repository: false,
+            duration: false,
+            ..LogFields::default()
+        },
+    };
+
+    // The path is not logged, so find the record by its delivery ID instead.
+    let request = fixtures::get(EventType::Issues, "opened")
+        .unwrap()
+        .request()
+        .id("/disabled")
+        .into_request();
+
+    let record = log(config, "/disabled", request).await;
+    let record: Value = serde_json::from_str(&record).unwrap();
+
+    assert!(record.get("remote_addr").is_none());
+    assert!(record.get("uri").is_none());
+    assert!(record.get("repository").is_none());
+    assert!(record.get("duration_ms").is_none());
+    assert_eq!(record["event"], "issues");
+    assert_eq!(record["status"], 200);
+}
+
+#[tokio::test]
+async fn text_records_are_one_line() {
+    let record = log(LogConfig::default(), "/text", opened()).await;
+
+    assert!(record.starts_with("[127.0.0.1] POST /text - 200 OK ("));
+    assert!(!record.contains('\n'));
+}
+
+#[tokio::test]
+async fn deliveries_can_be_logged_by_a_layer() {
+    capture();
+
+    let app = common::Noop.layer(DeliveryLoggerLayer::new(LogConfig::json()));
+    fixtures::get(EventType::Issues, "opened")
+        .unwrap()
+        .request()
+        .id("layer-1")
+        .send(&mut App::new(app))
+        .await
+        .unwrap();
+
+    let record: Value = serde_json::from_str(&find("layer-1")).unwrap();
+
+    assert_eq!(record["delivery"], "layer-1");
+    assert_eq!(record["event"], "issues");
+    assert_eq!(record["action"], "opened");
+    assert_eq!(record["installation"], 2311213);
+    assert_eq!(record["repository"], "Codertocat/Hello-World");
+    assert_eq!(record["status"], 200);
+    assert!(record.get("remote_addr").is_none());
+    assert!(record.get("error").is_none());
+}
+
+#[tokio::test]
+async fn layers_see_the_outcome_of_inner_layers() {
+    capture();
+
+    // The logger is outside of the timeout, so it sees the delivery time out.
+    let app = common::Gated::default()
+        .layer(TimeoutLayer::new(Duration::from_millis(10)))
+        .layer(DeliveryLoggerLayer::default());
+    fixtures::get(EventType::Issues, "opened")
+        .unwrap()
+        .request()
+        .id("layer-2")
+        .send(&mut App::new(app))
+        .await
+        .unwrap();
+
+    let record = find("layer-2");
+    assert!(
+        record.starts_with("issues.opened layer-2 - Timed out after 10ms ("),
+        "{}",
+        record
+    );
+}
@@ -0,0 +1,146 @@
+// Copyright (c) 2019 Jason White
+// Copyright (c) 2019 Mike Lubinets
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+#![cfg(feature = "fixtures")]
+
+mod common;
+
+use std::time::Duration;
+
+use github_app::{fixtures, App, EventType, OrderBy, Overflow};
+use tokio::task::JoinHandle;
+
+use common::Gated;
+
+fn send<T>(app: &App<T>, event: EventType, name: &str) -> JoinHandle<u16>
+where
+    T: github_app::GithubApp + Sync + Send + 'static,
+{
+    let fixture = fixtures::get(event, name).unwrap();
+    let mut app = app.clone();
+
+    tokio::spawn(async move {
+        fixture
+            .request()
+            .send(&mut app)
+            .await
+            .unwrap()
+            .status()
+            .as_u16()
+    })
+}
+
+/// Gives spawned deliveries a chance to start.
+async fn settle() {
+    tokio::time::delay_for(Duration::from_millis(50)).await;
+}
+
+#[tokio::test]
+async fn same_issue_is_sequential() {
+    let gated = Gated::default();
+    let app = App::new(gated.clone()).order_by(OrderBy::Issue);
+
+    // Both are about issue #1 of the same repository.
+    let first = send(&app, EventType::Issues, "opened");
+    gated.wait_started().await;
+
+    let second = send(&app, EventType::IssueComment, "created");
+    settle().await;
+    assert!(
+        gated.started.try_acquire().is_err(),
+        "second delivery started before the first one finished"
+    );
+
+    gated.release.add_permits(1);
+    assert_eq!(first.await.unwrap(), 200);
+
+    gated.wait_started().await;
+    gated.release.add_permits(1);
+    assert_eq!(second.await.unwrap(), 200);
+}
+
+#[tokio::test]
+async fn different_issues_are_concurrent() {
+    let gated = Gated::default();
+    let app = App::new(gated.clone()).order_by(OrderBy::Issue);
+
+    let issue = send(&app, EventType::Issues, "opened");
+    gated.wait_started().await;
+
+    // Pull request #2 has a different key, so it must not wait for issue #1.
+    let pull = send(&app, EventType::PullRequest, "opened");
+    tokio::time::timeout(Duration::from_secs(5), gated.wait_started())
+        .await
+        .expect("delivery for another issue was held back");
+
+    gated.release.add_permits(2);
+    assert_eq!(issue.await.unwrap(), 200);
+    assert_eq!(pull.await.unwrap(), 200);
+}
+
+#[tokio::test]
+async fn waiting_deliveries_do_not_hold_installation_permits() {
+    let gated = Gated::default();
+    let app = App::new(gated.clone())
+        .order_by(OrderBy::Issue)
+        .installation_concurrency_limit(2);
+
+    // All of these belong to the same installation.
+    let first = send(&app, EventType::Issues, "opened");
+    gated.wait_started().await;
+
+    let queued = send(&app, EventType::IssueComment, "created");
+    settle().await;
+
+    // Only the first delivery holds a permit, so there is one left.
+    let other = send(&app, EventType::PullRequest, "opened");
+    tokio::time::timeout(Duration::from_secs(5), gated.wait_started())
+        .await
+        .expect("queued delivery took the installation's last permit");
+
+    gated.release.add_permits(3);
+    assert_eq!(first.await.unwrap(), 200);
+    assert_eq!(queued.await.unwrap(), 200);
+    assert_eq!(other.await.unwrap(), 200);
+}
+
+#[tokio::test]
+async fn waiting_deliveries_do_not_hold_global_permits() {
+    let gated = Gated::default();
+    let app = App::new(gated.clone())
+        .order_by(OrderBy::Issue)
+        .concurrency_limit(1)
+        .overflow(Overflow::Reject {
+            retry_after: Duration::from_secs(1),
+        });
+
+    let first = send(&app, EventType::Issues, "opened");
+    gated.wait_started().await;
+
+    // This one is blocked by the first delivery's key. If it tried to take
+    // the only permit while waiting, it would be rejected.
+    let queued = send(&app, EventType::IssueComment, "created");
+    settle().await;
+
+    gated.release.add_permits(2);
+    assert_eq!(first.await.unwrap(), 200);
+    assert_eq!(queued.await.unwrap(), 200);
+    assert_eq!(app.metrics().rejected(), 0);
+}
@@ -0,0 +1,161 @@
+// Copyright (c) 2019 Jason White
+// Copyright (c) 2019 Mike Lubinets
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+#![cfg(feature = "fixtures")]
+
+mod common;
+
+use std::convert::Infallible;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use futures::{future, Future};
+use github_app::testing::{FakeGithub, TestRequest};
+use github_app::{App, ClientPool, Event, EventType, GithubApp};
+use hyper::Method;
+use serde_json::json;
+
+/// An app that counts how often it is called.
+#[derive(Clone)]
+struct Counting {
+    pool: Option<Arc<ClientPool>>,
+    calls: Arc<AtomicUsize>,
+}
+
+impl Counting {
+    fn new(pool: Option<ClientPool>) -> Self {
+        Counting {
+            pool: pool.map(Arc::new),
+            calls: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    fn calls(&self) -> usize {
+        self.calls.load(Ordering::SeqCst)
+    }
+}
+
+impl GithubApp for Counting {
+    type Error = Infallible;
+    type Future = Pin<Box<dyn Future<Output = Result<(), Self::Error>> + Send>>;
+
+    fn client_pool(&self) -> Option<Arc<ClientPool>> {
+        self.pool.clone()
+    }
+
+    fn call(&mut self, _event: Event) -> Self::Future {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        Box::pin(future::ok(()))
+    }
+}
+
+/// A comment posted by the given bot.
+fn comment_by(login: &str) -> TestRequest {
+    let mut payload =
+        common::payload(EventType::IssueComment, "created_by_bot");
+    payload["sender"]["login"] = json!(login);
+    payload["comment"]["user"]["login"] = json!(login);
+
+    TestRequest::new(EventType::IssueComment, payload.to_string())
+}
+
+#[tokio::test]
+async fn ignores_own_events_by_default() {
+    let github = FakeGithub::start().unwrap();
+    let counting = Counting::new(Some(github.client_pool()));
+    let mut app = App::new(counting.clone());
+
+    for _ in 0..2 {
+        let response =
+            comment_by("fake-app[bot]").send(&mut app).await.unwrap();
+        assert_eq!(response.status(), 200);
+    }
+
+    assert_eq!(counting.calls(), 0);
+
+    // The login is only requested once.
+    let requests = github.requests();
+    let logins = requests
+        .iter()
+        .filter(|r| r.method == Method::GET && r.path == "/app")
+        .count();
+    assert_eq!(logins, 1);
+}
+
+#[tokio::test]
+async fn passes_events_from_others() {
+    let github = FakeGithub::start().unwrap();
+    let counting = Counting::new(Some(github.client_pool()));
+    let mut app = App::new(counting.clone());
+
+    comment_by("other-app[bot]").send(&mut app).await.unwrap();
+
+    let fixture = common::payload(EventType::IssueComment, "created");
+    TestRequest::new(EventType::IssueComment, fixture.to_string())
+        .send(&mut app)
+        .await
+        .unwrap();
+
+    assert_eq!(counting.calls(), 2);
+
+    // A login is only needed for events sent by bots.
+    assert_eq!(
+        github
+            .requests()
+            .iter()
+            .filter(|r| r.path == "/app")
+            .count(),
+        1
+    );
+}
+
+#[tokio::test]
+async fn allowed_own_events_are_passed() {
+    let github = FakeGithub::start().unwrap();
+    let counting = Counting::new(Some(github.client_pool()));
+    let mut app =
+        App::new(counting.clone()).allow_own_events(EventType::IssueComment);
+
+    comment_by("fake-app[bot]").send(&mut app).await.unwrap();
+
+    assert_eq!(counting.calls(), 1);
+}
+
+#[tokio::test]
+async fn login_can_be_set() {
+    let counting = Counting::new(None);
+    let mut app = App::new(counting.clone()).bot_login("my-app[bot]");
+
+    comment_by("my-app[bot]").send(&mut app).await.unwrap();
+    comment_by("fake-app[bot]").send(&mut app).await.unwrap();
+
+    assert_eq!(counting.calls(), 1);
+}
+
+#[tokio::test]
+async fn events_are_passed_without_a_login() {
+    let counting = Counting::new(None);
+    let mut app = App::new(counting.clone());
+
+    comment_by("fake-app[bot]").send(&mut app).await.unwrap();
+
+    assert_eq!(counting.calls(), 1);
+}
@@ -0,0 +1,98 @@
+// Copyright (c) 2019 Jason White
+// Copyright (c) 2019 Mike Lubinets
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+#![cfg(feature = "fixtures")]
+
+use std::convert::Infallible;
+use std::pin::Pin;
+use std::time::Duration;
+
+use futures::Future;
+use github_app::testing::body_string;
+use github_app::{fixtures, App, Event, EventType, GithubApp, Overflow};
+
+/// An app that panics, either right away or once its future is polled.
+#[derive(Clone)]
+struct Panics {
+    in_future: bool,
+}
+
+impl GithubApp for Panics {
+    type Error = Infallible;
+    type Future = Pin<Box<dyn Future<Output = Result<(), Self::Error>> + Send>>;
+
+    fn call(&mut self, _event: Event) -> Self::Future {
+        if !self.in_future {
+            panic!("oh no");
+        }
+
+        Box::pin(async { panic!("oh no") })
+    }
+}
+
+#[tokio::test]
+async fn panics_are_internal_server_errors() {
+    for in_future in &[false, true] {
+        let app = App::new(Panics {
+            in_future: *in_future,
+        });
+        let fixture = fixtures::get(EventType::Issues, "opened").unwrap();
+
+        let response = fixture.request().send(&mut app.clone()).await.unwrap();
+
+        assert_eq!(response.status(), 500);
+        assert_eq!(body_string(response).await.unwrap(), "Panicked: oh no");
+        assert_eq!(app.metrics().panics(), 1);
+    }
+}
+
+#[tokio::test]
+async fn panic_messages_can_be_hidden() {
+    let app = App::new(Panics { in_future: true }).hide_errors(true);
+    let fixture = fixtures::get(EventType::Issues, "opened").unwrap();
+
+    let response = fixture.request().send(&mut app.clone()).await.unwrap();
+
+    assert_eq!(response.status(), 500);
+    assert_eq!(
+        body_string(response).await.unwrap(),
+        "Internal Server Error"
+    );
+}
+
+#[tokio::test]
+async fn panics_release_their_permits() {
+    let app = App::new(Panics { in_future: true })
+        .concurrency_limit(1)
+        .overflow(Overflow::Reject {
+            retry_after: Duration::from_secs(1),
+        });
+    let fixture = fixtures::get(EventType::Issues, "opened").unwrap();
+
+    // If the first panic kept the only permit, the second delivery would be
+    // rejected with 503.
+    for _ in 0..2 {
+        let response = fixture.request().send(&mut app.clone()).await.unwrap();
+        assert_eq!(response.status(), 500);
+    }
+
+    assert_eq!(app.metrics().panics(), 2);
+    assert_eq!(app.metrics().rejected(), 0);
+}
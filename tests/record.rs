@@ -0,0 +1,129 @@
+// Copyright (c) 2019 Jason White
+// Copyright (c) 2019 Mike Lubinets
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::path::Path;
+use std::sync::Arc;
+
+use chrono::Utc;
+use futures::future::poll_fn;
+use github_app::record::{self, RecordedDelivery};
+use github_app::{Recorder, Recording};
+use hyper::{
+    service::{service_fn, Service},
+    Body, Request, Response,
+};
+
+fn delivery(n: usize) -> RecordedDelivery {
+    let mut headers = BTreeMap::new();
+    headers.insert("x-github-delivery".to_string(), n.to_string());
+    headers.insert("x-github-event".to_string(), "ping".to_string());
+
+    RecordedDelivery {
+        timestamp: Utc::now(),
+        headers,
+        body: br#"{"zen":"Keep it logically awesome."}"#.to_vec(),
+    }
+}
+
+/// The IDs of the deliveries in a recording, in order.
+fn ids(path: &Path) -> Vec<String> {
+    record::read(path)
+        .unwrap()
+        .iter()
+        .map(|d| d.id().unwrap().to_string())
+        .collect()
+}
+
+#[test]
+fn recording_appends() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("deliveries.jsonl");
+
+    let recording = Recording::new(&path);
+    for n in 0..3 {
+        recording.append(&delivery(n)).unwrap();
+    }
+
+    // Appending to an existing file keeps what is already there.
+    let recording = Recording::new(&path);
+    recording.append(&delivery(3)).unwrap();
+
+    assert_eq!(ids(&path), ["0", "1", "2", "3"]);
+}
+
+#[test]
+fn recording_rotates() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("deliveries.jsonl");
+
+    // Every delivery has the same size, so each file fits exactly two.
+    let line = serde_json::to_vec(&delivery(0)).unwrap().len() as u64 + 1;
+    let recording = Recording::new(&path).rotate(2 * line, 2);
+
+    for n in 0..7 {
+        recording.append(&delivery(n)).unwrap();
+    }
+
+    assert_eq!(ids(&path), ["6"]);
+    assert_eq!(ids(&dir.path().join("deliveries.jsonl.1")), ["4", "5"]);
+    assert_eq!(ids(&dir.path().join("deliveries.jsonl.2")), ["2", "3"]);
+    assert!(!dir.path().join("deliveries.jsonl.3").exists());
+}
+
+#[test]
+fn recording_without_history() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("deliveries.jsonl");
+
+    let line = serde_json::to_vec(&delivery(0)).unwrap().len() as u64 + 1;
+    let recording = Recording::new(&path).rotate(line, 0);
+
+    for n in 0..3 {
+        recording.append(&delivery(n)).unwrap();
+    }
+
+    assert_eq!(ids(&path), ["2"]);
+    assert!(!dir.path().join("deliveries.jsonl.1").exists());
+}
+
+#[tokio::test]
+async fn recorder_keeps_the_raw_body() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("deliveries.jsonl");
+
+    let service = service_fn(|_: Request<Body>| async {
+        Ok::<_, Infallible>(Response::new(Body::empty()))
+    });
+    let mut recorder = Recorder::new(Arc::new(Recording::new(&path)), service);
+
+    // This isn't valid UTF-8, so it would be mangled (and its signature would
+    // no longer match) if it was stored as a string.
+    let body = b"{\"zen\":\"\xff\"}".to_vec();
+    let request = Request::post("/").body(Body::from(body.clone())).unwrap();
+
+    poll_fn(|cx| recorder.poll_ready(cx)).await.unwrap();
+    recorder.call(request).await.unwrap();
+
+    let delivery = record::read(&path).unwrap().remove(0);
+    assert_eq!(delivery.body, body);
+    assert_eq!(delivery.into_webhook_request().unwrap().body, body);
+}
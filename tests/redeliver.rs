@@ -0,0 +1,128 @@
+// Copyright (c) 2019 Jason White
+// Copyright (c) 2019 Mike Lubinets
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+#![cfg(feature = "testing")]
+
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use github_app::testing::FakeGithub;
+use github_app::Redelivery;
+use hyper::{http::StatusCode, Method};
+use serde_json::{json, Value};
+
+fn time(s: &str) -> DateTime<Utc> {
+    s.parse().unwrap()
+}
+
+fn attempt(id: u64, guid: &str, delivered_at: &str, status_code: u16) -> Value {
+    json!({
+        "id": id,
+        "guid": guid,
+        "delivered_at": delivered_at,
+        "redelivery": false,
+        "status": if status_code == 200 { "OK" } else { "Failed" },
+        "status_code": status_code,
+        "event": "issues",
+        "action": "opened",
+        "installation_id": 1,
+        "repository_id": 1,
+    })
+}
+
+/// Starts a fake API with a delivery log, newest first.
+fn github() -> FakeGithub {
+    let github = FakeGithub::start().unwrap();
+
+    github.route(
+        Method::GET,
+        "/app/hook/deliveries",
+        StatusCode::OK,
+        json!([
+            // "a" failed at first, but a redelivery succeeded.
+            attempt(5, "a", "2020-01-01T12:00:00Z", 200),
+            attempt(4, "c", "2020-01-01T11:00:00Z", 500),
+            attempt(3, "b", "2020-01-01T10:30:00Z", 0),
+            attempt(2, "b", "2020-01-01T10:00:00Z", 502),
+            attempt(1, "a", "2020-01-01T09:00:00Z", 500),
+        ]),
+    );
+    github.route(
+        Method::POST,
+        "/app/hook/deliveries/*/attempts",
+        StatusCode::ACCEPTED,
+        json!({}),
+    );
+
+    github
+}
+
+#[tokio::test]
+async fn finds_deliveries_that_never_succeeded() {
+    let github = github();
+    let pool = github.client_pool();
+
+    let failed = Redelivery::new(time("2020-01-01T08:00:00Z"))
+        .dry_run(true)
+        .run(&pool)
+        .await
+        .unwrap();
+
+    // One entry per delivery, for its latest attempt, oldest delivery first.
+    let ids: Vec<_> = failed.iter().map(|d| (d.guid.as_str(), d.id)).collect();
+    assert_eq!(ids, [("b", 3), ("c", 4)]);
+
+    assert!(!github.requests().iter().any(|r| r.method == Method::POST));
+}
+
+#[tokio::test]
+async fn until_uses_first_attempt() {
+    let github = github();
+    let pool = github.client_pool();
+
+    // "b" was first attempted before the cut-off, even though its latest
+    // attempt is after it.
+    let failed = Redelivery::new(time("2020-01-01T08:00:00Z"))
+        .until(time("2020-01-01T10:15:00Z"))
+        .dry_run(true)
+        .run(&pool)
+        .await
+        .unwrap();
+
+    let guids: Vec<_> = failed.iter().map(|d| d.guid.as_str()).collect();
+    assert_eq!(guids, ["b"]);
+}
+
+#[tokio::test]
+async fn redelivers_latest_attempts() {
+    let github = github();
+    let pool = github.client_pool();
+
+    Redelivery::new(time("2020-01-01T08:00:00Z"))
+        .interval(Duration::from_millis(0))
+        .run(&pool)
+        .await
+        .unwrap();
+
+    assert!(github.received(Method::POST, "/app/hook/deliveries/3/attempts"));
+    assert!(github.received(Method::POST, "/app/hook/deliveries/4/attempts"));
+    assert!(!github.received(Method::POST, "/app/hook/deliveries/1/attempts"));
+    assert!(!github.received(Method::POST, "/app/hook/deliveries/2/attempts"));
+}
@@ -0,0 +1,150 @@
+// Copyright (c) 2019 Jason White
+// Copyright (c) 2019 Mike Lubinets
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+#![cfg(feature = "fixtures")]
+
+use std::fmt;
+use std::io;
+use std::pin::Pin;
+
+use futures::{future, Future};
+use github_app::testing::{body_string, TestRequest};
+use github_app::{fixtures, App, Event, EventType, GithubApp, IntoResponse};
+use hyper::header::{HeaderMap, HeaderValue, RETRY_AFTER};
+use hyper::http::StatusCode;
+
+/// An app that always fails with the error returned by the function.
+struct Fails<E>(fn() -> E);
+
+impl<E> Clone for Fails<E> {
+    fn clone(&self) -> Self {
+        Fails(self.0)
+    }
+}
+
+impl<E> GithubApp for Fails<E>
+where
+    E: IntoResponse + Send + 'static,
+{
+    type Error = E;
+    type Future = Pin<Box<dyn Future<Output = Result<(), Self::Error>> + Send>>;
+
+    fn call(&mut self, _event: Event) -> Self::Future {
+        Box::pin(future::err((self.0)()))
+    }
+}
+
+/// An error with its own response.
+#[derive(Debug)]
+struct RateLimited;
+
+impl fmt::Display for RateLimited {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Rate limit of installation 42 exceeded")
+    }
+}
+
+impl IntoResponse for RateLimited {
+    fn status(&self) -> StatusCode {
+        StatusCode::TOO_MANY_REQUESTS
+    }
+
+    fn body(&self) -> String {
+        "Rate limited, try again later".into()
+    }
+
+    fn headers(&self) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("60"));
+        headers
+    }
+}
+
+fn rate_limited() -> RateLimited {
+    RateLimited
+}
+
+fn io_error() -> io::Error {
+    io::Error::new(io::ErrorKind::Other, "disk full")
+}
+
+fn opened() -> TestRequest {
+    fixtures::get(EventType::Issues, "opened")
+        .unwrap()
+        .request()
+}
+
+#[tokio::test]
+async fn errors_choose_their_response() {
+    let mut app = App::new(Fails(rate_limited));
+
+    let response = opened().send(&mut app).await.unwrap();
+
+    assert_eq!(response.status(), 429);
+    assert_eq!(response.headers()[RETRY_AFTER], "60");
+    assert_eq!(
+        body_string(response).await.unwrap(),
+        "Rate limited, try again later"
+    );
+}
+
+#[tokio::test]
+async fn errors_default_to_internal_server_error() {
+    let mut app = App::new(Fails(io_error));
+
+    let response = opened().send(&mut app).await.unwrap();
+
+    assert_eq!(response.status(), 500);
+    assert_eq!(body_string(response).await.unwrap(), "disk full");
+}
+
+#[tokio::test]
+async fn hidden_errors_keep_status_and_headers() {
+    let mut app = App::new(Fails(rate_limited)).hide_errors(true);
+
+    let response = opened().send(&mut app).await.unwrap();
+
+    assert_eq!(response.status(), 429);
+    assert_eq!(response.headers()[RETRY_AFTER], "60");
+    assert_eq!(body_string(response).await.unwrap(), "Too Many Requests");
+
+    let mut app = App::new(Fails(io_error)).hide_errors(true);
+
+    let response = opened().send(&mut app).await.unwrap();
+
+    assert_eq!(response.status(), 500);
+    assert_eq!(
+        body_string(response).await.unwrap(),
+        "Internal Server Error"
+    );
+}
+
+#[tokio::test]
+async fn rejected_requests_are_not_hidden() {
+    let mut app = App::new(Fails(io_error)).hide_errors(true);
+
+    let response = TestRequest::new(EventType::Issues, "not json")
+        .send(&mut app)
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), 400);
+    assert_ne!(body_string(response).await.unwrap(), "Bad Request");
+}
@@ -0,0 +1,206 @@
+// Copyright (c) 2019 Jason White
+// Copyright (c) 2019 Mike Lubinets
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+#![cfg(feature = "fixtures")]
+
+mod common;
+
+use std::fs;
+use std::io::Cursor;
+use std::net::{SocketAddr, TcpListener};
+use std::path::Path;
+use std::time::Duration;
+
+use chrono::Utc;
+use futures::StreamExt;
+use github_app::record::RecordedDelivery;
+use github_app::testing::TestRequest;
+use github_app::{
+    fixtures, source, App, EventType, HttpSource, JsonLines, Source, Spool,
+};
+use hyper::{Body, Client, Request};
+
+use common::Noop;
+
+/// A delivery for an opened issue, as a recording would have it.
+fn recorded() -> RecordedDelivery {
+    let request = fixtures::get(EventType::Issues, "opened")
+        .unwrap()
+        .request();
+    let body = request.body().to_vec();
+
+    RecordedDelivery {
+        timestamp: Utc::now(),
+        headers: request
+            .into_request()
+            .headers()
+            .iter()
+            .map(|(name, value)| {
+                (name.to_string(), value.to_str().unwrap().to_string())
+            })
+            .collect(),
+        body,
+    }
+}
+
+/// Spools a delivery under the given file name.
+fn spool(dir: &Path, name: &str, delivery: &RecordedDelivery) {
+    fs::write(dir.join(name), serde_json::to_vec(delivery).unwrap()).unwrap();
+}
+
+/// The names of the files in a directory, sorted.
+fn files(dir: &Path) -> Vec<String> {
+    let mut names: Vec<_> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap())
+        .filter(|entry| entry.file_type().unwrap().is_file())
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .collect();
+    names.sort();
+    names
+}
+
+#[tokio::test]
+async fn spool_removes_handled_deliveries() {
+    let dir = tempfile::tempdir().unwrap();
+    spool(dir.path(), "1.json", &recorded());
+    spool(dir.path(), "2.json", &recorded());
+
+    // Files that are still being written are left alone.
+    fs::write(dir.path().join("3.json.tmp"), "{").unwrap();
+    fs::write(dir.path().join(".4.json"), "{").unwrap();
+
+    let spool = Spool::new(dir.path()).watch(false);
+    source::run(spool, App::new(Noop), 1).await.unwrap();
+
+    assert_eq!(files(dir.path()), [".4.json", "3.json.tmp"]);
+    assert!(!dir.path().join("failed").exists());
+}
+
+#[tokio::test]
+async fn spool_moves_failed_deliveries() {
+    let dir = tempfile::tempdir().unwrap();
+    spool(dir.path(), "1.json", &recorded());
+    fs::write(dir.path().join("2.json"), "not a delivery").unwrap();
+
+    // The app rejects this one, because the payload isn't a push.
+    let mut rejected = recorded();
+    rejected
+        .headers
+        .insert("x-github-event".into(), "push".into());
+    spool(dir.path(), "3.json", &rejected);
+
+    let spool = Spool::new(dir.path()).watch(false);
+    source::run(spool, App::new(Noop), 1).await.unwrap();
+
+    assert!(files(dir.path()).is_empty());
+    assert_eq!(files(&dir.path().join("failed")), ["2.json", "3.json"]);
+}
+
+#[tokio::test]
+async fn spool_picks_up_in_flight_deliveries_after_a_restart() {
+    let dir = tempfile::tempdir().unwrap();
+    let delivery = recorded();
+    spool(dir.path(), "1.json", &delivery);
+
+    // The delivery is taken, but the process stops before it is handled.
+    let mut stream = Spool::new(dir.path()).watch(false).into_stream();
+    let incoming = stream.next().await.unwrap().unwrap();
+    drop(incoming);
+    assert!(stream.next().await.is_none());
+    drop(stream);
+
+    assert_eq!(files(dir.path()), ["1.json"]);
+
+    let mut stream = Spool::new(dir.path()).watch(false).into_stream();
+    let incoming = stream.next().await.unwrap().unwrap();
+    assert_eq!(
+        incoming.request.headers["x-github-delivery"],
+        delivery.id().unwrap()
+    );
+    assert!(stream.next().await.is_none());
+}
+
+#[tokio::test]
+async fn json_lines_skip_invalid_lines() {
+    let first = recorded();
+    let second = recorded();
+
+    let input = format!(
+        "{}\n\nnot a delivery\n{{\"headers\":{{}}}}\n{}\n",
+        serde_json::to_string(&first).unwrap(),
+        serde_json::to_string(&second).unwrap(),
+    );
+
+    let ids: Vec<_> = JsonLines::new(Cursor::new(input.into_bytes()))
+        .into_stream()
+        .map(|incoming| {
+            let incoming = incoming.unwrap();
+            incoming.request.headers["x-github-delivery"]
+                .to_str()
+                .unwrap()
+                .to_string()
+        })
+        .collect()
+        .await;
+
+    assert_eq!(ids, [first.id().unwrap(), second.id().unwrap()]);
+}
+
+/// An address that nothing is listening on.
+fn unused_addr() -> SocketAddr {
+    TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+}
+
+/// Sends a webhook request to a server, waiting for it to start listening.
+async fn post(addr: SocketAddr, request: TestRequest) -> u16 {
+    let body = request.body().to_vec();
+    let headers = request.into_request().headers().clone();
+
+    for _ in 0..50 {
+        let mut req = Request::post(format!("http://{}/", addr))
+            .body(Body::from(body.clone()))
+            .unwrap();
+        *req.headers_mut() = headers.clone();
+
+        match Client::new().request(req).await {
+            Ok(response) => return response.status().as_u16(),
+            Err(_) => tokio::time::delay_for(Duration::from_millis(20)).await,
+        }
+    }
+
+    panic!("server did not start");
+}
+
+#[tokio::test]
+async fn http_source_replies_with_the_app_status() {
+    let addr = unused_addr();
+    tokio::spawn(source::run(HttpSource::bind(addr), App::new(Noop), 0));
+
+    let fixture = fixtures::get(EventType::Issues, "opened").unwrap();
+    assert_eq!(post(addr, fixture.request()).await, 200);
+
+    // Failures are passed on as well.
+    let request = TestRequest::new(EventType::Push, "{}");
+    assert_eq!(post(addr, request).await, 400);
+}
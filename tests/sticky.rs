@@ -0,0 +1,201 @@
+// Copyright (c) 2019 Jason White
+// Copyright (c) 2019 Mike Lubinets
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+#![cfg(feature = "testing")]
+
+use github_app::testing::FakeGithub;
+use github_app::StickyComment;
+use hyper::{http::StatusCode, Method};
+use serde_json::{json, Value};
+
+const REPOSITORY: &str = "Codertocat/Hello-World";
+const MARKER: &str = "<!-- github-app:sticky:coverage -->";
+
+fn comment(id: u64, login: &str, body: &str) -> Value {
+    json!({
+        "id": id,
+        "user": { "login": login, "type": "Bot" },
+        "body": body,
+    })
+}
+
+/// Starts a fake API where issue #1 has the given comments.
+fn github(comments: Vec<Value>) -> FakeGithub {
+    let github = FakeGithub::start().unwrap();
+
+    github.route(
+        Method::GET,
+        "/repos/Codertocat/Hello-World/issues/1/comments",
+        StatusCode::OK,
+        Value::Array(comments),
+    );
+
+    github
+}
+
+/// The bodies of the requests with the given method and path.
+fn bodies(github: &FakeGithub, method: Method, path: &str) -> Vec<Value> {
+    github
+        .requests()
+        .iter()
+        .filter(|r| r.method == method && r.path == path)
+        .map(|r| r.json().unwrap()["body"].clone())
+        .collect()
+}
+
+#[tokio::test]
+async fn creates_comment() {
+    let github = github(vec![]);
+    let sticky = StickyComment::new("coverage");
+
+    sticky
+        .upsert(&github.client_pool(), 1, REPOSITORY, 1, "Coverage: 87%\n")
+        .await
+        .unwrap();
+
+    assert_eq!(
+        bodies(
+            &github,
+            Method::POST,
+            "/repos/Codertocat/Hello-World/issues/1/comments"
+        ),
+        vec![json!(format!("{}\nCoverage: 87%", MARKER))]
+    );
+}
+
+#[tokio::test]
+async fn updates_only_own_comment() {
+    let body = format!("{}\nCoverage: 80%", MARKER);
+    let github = github(vec![
+        comment(10, "Codertocat", &body),
+        comment(11, "other-app[bot]", &body),
+        comment(12, "fake-app[bot]", &body),
+    ]);
+    let sticky = StickyComment::new("coverage");
+
+    let id = sticky
+        .upsert(&github.client_pool(), 1, REPOSITORY, 1, "Coverage: 87%")
+        .await
+        .unwrap();
+
+    assert_eq!(id, 12);
+    assert_eq!(
+        bodies(
+            &github,
+            Method::PATCH,
+            "/repos/Codertocat/Hello-World/issues/comments/12"
+        ),
+        vec![json!(format!("{}\nCoverage: 87%", MARKER))]
+    );
+    assert!(bodies(
+        &github,
+        Method::POST,
+        "/repos/Codertocat/Hello-World/issues/1/comments"
+    )
+    .is_empty());
+}
+
+#[tokio::test]
+async fn ignores_comments_by_others() {
+    let body = format!("{}\nCoverage: 80%", MARKER);
+    let github = github(vec![
+        comment(10, "Codertocat", &body),
+        comment(11, "other-app[bot]", &body),
+    ]);
+    let sticky = StickyComment::new("coverage");
+
+    let found = sticky
+        .find(&github.client_pool(), 1, REPOSITORY, 1)
+        .await
+        .unwrap();
+
+    assert_eq!(found, None);
+}
+
+#[tokio::test]
+async fn skips_unchanged_content() {
+    let github = github(vec![comment(
+        12,
+        "fake-app[bot]",
+        &format!("{}\nCoverage: 87%", MARKER),
+    )]);
+    let sticky = StickyComment::new("coverage");
+
+    sticky
+        .upsert(&github.client_pool(), 1, REPOSITORY, 1, "Coverage: 87%")
+        .await
+        .unwrap();
+
+    assert!(
+        github
+            .requests()
+            .iter()
+            .all(|r| r.method == Method::GET
+                || r.path.ends_with("/access_tokens"))
+    );
+}
+
+#[tokio::test]
+async fn collapses_previous_content() {
+    let github = github(vec![comment(
+        12,
+        "fake-app[bot]",
+        &format!("{}\nCoverage: 80%", MARKER),
+    )]);
+    let sticky = StickyComment::new("coverage").collapse_previous(true);
+
+    sticky
+        .upsert(&github.client_pool(), 1, REPOSITORY, 1, "Coverage: 87%")
+        .await
+        .unwrap();
+
+    assert_eq!(
+        bodies(
+            &github,
+            Method::PATCH,
+            "/repos/Codertocat/Hello-World/issues/comments/12"
+        ),
+        vec![json!(format!(
+            "{}\nCoverage: 87%\n\n<!-- github-app:previous -->\n\
+             <details><summary>Previous version</summary>\n\n\
+             Coverage: 80%\n\n</details>",
+            MARKER
+        ))]
+    );
+}
+
+#[tokio::test]
+async fn deletes_own_comment() {
+    let github = github(vec![comment(
+        12,
+        "fake-app[bot]",
+        &format!("{}\nCoverage: 80%", MARKER),
+    )]);
+    let sticky = StickyComment::new("coverage");
+
+    assert!(sticky
+        .delete(&github.client_pool(), 1, REPOSITORY, 1)
+        .await
+        .unwrap());
+    assert!(github.received(
+        Method::DELETE,
+        "/repos/Codertocat/Hello-World/issues/comments/12"
+    ));
+}
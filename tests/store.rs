@@ -0,0 +1,144 @@
+// Copyright (c) 2019 Jason White
+// Copyright (c) 2019 Mike Lubinets
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+use std::sync::Arc;
+
+use github_app::{
+    FileStore, MemoryStore, Scope, ScopedStore, StateStore, StateStoreExt,
+};
+use serde_json::json;
+
+fn round_trip<S>(store: &S)
+where
+    S: StateStore,
+{
+    let issue = Scope::issue(1, "octocat/hello-world", 42);
+    let repository = Scope::repository(1, "octocat/hello-world");
+
+    assert_eq!(store.get(&issue, "comment").unwrap(), None);
+
+    store.store(&issue, "comment", &1234u64).unwrap();
+    store.set(&repository, "comment", json!("other")).unwrap();
+
+    // Scopes are independent of each other.
+    assert_eq!(store.load::<u64>(&issue, "comment").unwrap(), Some(1234));
+    assert_eq!(
+        store.get(&repository, "comment").unwrap(),
+        Some(json!("other"))
+    );
+
+    assert_eq!(store.remove(&issue, "comment").unwrap(), Some(json!(1234)));
+    assert_eq!(store.get(&issue, "comment").unwrap(), None);
+    assert_eq!(store.remove(&issue, "comment").unwrap(), None);
+}
+
+#[test]
+fn memory_store_round_trip() {
+    round_trip(&MemoryStore::new());
+}
+
+#[test]
+fn file_store_round_trip() {
+    let dir = tempfile::tempdir().unwrap();
+    round_trip(&FileStore::new(dir.path()).unwrap());
+
+    // The values survive a new store in the same directory.
+    let scope = Scope::installation(7);
+    FileStore::new(dir.path())
+        .unwrap()
+        .store(&scope, "seen", &["a", "b"])
+        .unwrap();
+
+    let store = FileStore::new(dir.path()).unwrap();
+    assert_eq!(
+        store.load::<Vec<String>>(&scope, "seen").unwrap(),
+        Some(vec!["a".to_string(), "b".to_string()])
+    );
+}
+
+#[test]
+fn file_store_layout() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = FileStore::new(dir.path()).unwrap();
+
+    store
+        .set(&Scope::installation(1), "key", json!(true))
+        .unwrap();
+    store
+        .set(
+            &Scope::issue(1, "octocat/hello-world", 42),
+            "key",
+            json!(true),
+        )
+        .unwrap();
+
+    assert!(dir.path().join("1/state.json").is_file());
+    assert!(dir.path().join("1/octocat/hello-world/42.json").is_file());
+
+    // Removing the last value removes the file.
+    store.remove(&Scope::installation(1), "key").unwrap();
+    assert!(!dir.path().join("1/state.json").exists());
+}
+
+#[test]
+fn file_store_sanitizes_repository_names() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = FileStore::new(dir.path().join("state")).unwrap();
+
+    let scope = Scope::repository(1, "../.ssh");
+    store.set(&scope, "key", json!(true)).unwrap();
+
+    // Leading dots are encoded, so the path can't leave the directory or
+    // create hidden files.
+    assert!(dir.path().join("state/1/%2E./%2Essh/state.json").is_file());
+    assert!(!dir.path().join(".ssh").exists());
+    assert_eq!(store.get(&scope, "key").unwrap(), Some(json!(true)));
+}
+
+#[test]
+fn file_store_keeps_similar_names_apart() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = FileStore::new(dir.path()).unwrap();
+
+    let names = ["octo/.github", "octo/_github", "octo/%2Egithub"];
+    for (i, name) in names.iter().enumerate() {
+        store
+            .set(&Scope::repository(1, *name), "key", json!(i))
+            .unwrap();
+    }
+
+    for (i, name) in names.iter().enumerate() {
+        let value = store.get(&Scope::repository(1, *name), "key").unwrap();
+        assert_eq!(value, Some(json!(i)));
+    }
+}
+
+#[test]
+fn scoped_store() {
+    let store: Arc<dyn StateStore> = Arc::new(MemoryStore::new());
+    let scope = Scope::issue(1, "octocat/hello-world", 42);
+    let scoped = ScopedStore::new(store.clone(), scope.clone());
+
+    scoped.store("count", &3).unwrap();
+    assert_eq!(store.load::<u32>(&scope, "count").unwrap(), Some(3));
+
+    let installation = scoped.with_scope(Scope::installation(1));
+    assert_eq!(installation.get("count").unwrap(), None);
+}
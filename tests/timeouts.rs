@@ -0,0 +1,92 @@
+// Copyright (c) 2019 Jason White
+// Copyright (c) 2019 Mike Lubinets
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+#![cfg(feature = "fixtures")]
+
+mod common;
+
+use std::time::Duration;
+
+use github_app::{fixtures, App, EventType};
+use tokio::time::{delay_for, timeout};
+
+use common::Gated;
+
+/// Guards the tests against hanging forever if a timeout never fires.
+const SAFETY_NET: Duration = Duration::from_secs(10);
+
+#[tokio::test]
+async fn slow_deliveries_time_out() {
+    let app = App::new(Gated::default()).timeout(Duration::from_millis(50));
+    let fixture = fixtures::get(EventType::Issues, "opened").unwrap();
+
+    let response =
+        timeout(SAFETY_NET, fixture.request().send(&mut app.clone()))
+            .await
+            .unwrap()
+            .unwrap();
+
+    assert_eq!(response.status(), 504);
+    assert_eq!(app.metrics().timeouts(), 1);
+}
+
+#[tokio::test]
+async fn event_timeouts_take_precedence() {
+    let gated = Gated::default();
+    let app = App::new(gated.clone())
+        .timeout(Duration::from_millis(50))
+        .event_timeout(EventType::Issues, SAFETY_NET);
+    let issue = fixtures::get(EventType::Issues, "opened").unwrap();
+    let pull = fixtures::get(EventType::PullRequest, "opened").unwrap();
+
+    // The issue outlives the default timeout but not its own.
+    let mut first = app.clone();
+    let handle = tokio::spawn(async move {
+        issue.request().send(&mut first).await.unwrap().status()
+    });
+    gated.wait_started().await;
+    delay_for(Duration::from_millis(100)).await;
+    gated.release.add_permits(1);
+    assert_eq!(timeout(SAFETY_NET, handle).await.unwrap().unwrap(), 200);
+
+    // Other events still get the default timeout.
+    let response = timeout(SAFETY_NET, pull.request().send(&mut app.clone()))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(response.status(), 504);
+    assert_eq!(app.metrics().timeouts(), 1);
+}
+
+#[tokio::test]
+async fn event_timeouts_can_be_shorter() {
+    let app = App::new(Gated::default())
+        .timeout(SAFETY_NET)
+        .event_timeout(EventType::Issues, Duration::from_millis(50));
+    let fixture = fixtures::get(EventType::Issues, "opened").unwrap();
+
+    let response =
+        timeout(SAFETY_NET, fixture.request().send(&mut app.clone()))
+            .await
+            .unwrap()
+            .unwrap();
+
+    assert_eq!(response.status(), 504);
+}
@@ -0,0 +1,187 @@
+// Copyright (c) 2019 Jason White
+// Copyright (c) 2019 Mike Lubinets
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+#![cfg(feature = "fixtures")]
+
+mod common;
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use futures::compat::Future01CompatExt;
+use github_app::testing::FakeGithub;
+use github_app::{fixtures, App, EventType, TraceContext};
+use hyper::Method;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+
+const DELIVERY: &str = "72d3162e-cc78-11e3-81ab-4c9367dc0958";
+
+type Fields = BTreeMap<String, String>;
+
+/// A subscriber that remembers the name and fields of every span.
+#[derive(Clone, Default)]
+struct Spans {
+    next: Arc<AtomicU64>,
+    spans: Arc<Mutex<BTreeMap<u64, (&'static str, Fields)>>>,
+}
+
+impl Spans {
+    /// The fields of all spans with the given name.
+    fn named(&self, name: &str) -> Vec<Fields> {
+        self.spans
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|(n, _)| *n == name)
+            .map(|(_, fields)| fields.clone())
+            .collect()
+    }
+}
+
+struct Visitor<'a>(&'a mut Fields);
+
+impl Visit for Visitor<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0.insert(field.name().into(), format!("{:?}", value));
+    }
+}
+
+impl Subscriber for Spans {
+    fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, span: &Attributes<'_>) -> Id {
+        let id = self.next.fetch_add(1, Ordering::SeqCst) + 1;
+        let mut fields = Fields::new();
+        span.record(&mut Visitor(&mut fields));
+        self.spans
+            .lock()
+            .unwrap()
+            .insert(id, (span.metadata().name(), fields));
+        Id::from_u64(id)
+    }
+
+    fn record(&self, span: &Id, values: &Record<'_>) {
+        if let Some((_, fields)) =
+            self.spans.lock().unwrap().get_mut(&span.into_u64())
+        {
+            values.record(&mut Visitor(fields));
+        }
+    }
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, _event: &Event<'_>) {}
+
+    fn enter(&self, _span: &Id) {}
+
+    fn exit(&self, _span: &Id) {}
+}
+
+#[test]
+fn trace_context_is_derived_from_delivery() {
+    let context = TraceContext::from_delivery_id(DELIVERY).unwrap();
+
+    assert_eq!(context.trace_id(), 0x72d3162ecc7811e381ab4c9367dc0958);
+    assert_eq!(Some(context), TraceContext::from_delivery_id(DELIVERY));
+    assert_eq!(
+        context.traceparent(),
+        format!(
+            "00-72d3162ecc7811e381ab4c9367dc0958-{:016x}-01",
+            context.parent_id()
+        )
+    );
+    assert_ne!(context.parent_id(), 0);
+
+    assert_eq!(TraceContext::from_delivery_id("not-a-guid"), None);
+    assert_eq!(
+        TraceContext::from_delivery_id("00000000-0000-0000-0000-000000000000"),
+        None
+    );
+}
+
+#[tokio::test]
+async fn deliveries_are_handled_in_a_span() {
+    let spans = Spans::default();
+    let _guard = tracing::subscriber::set_default(spans.clone());
+
+    let fixture = fixtures::get(EventType::Issues, "opened").unwrap();
+    fixture
+        .request()
+        .id(DELIVERY)
+        .send(&mut App::new(common::Noop))
+        .await
+        .unwrap();
+
+    let delivery = &spans.named("delivery")[0];
+    assert_eq!(delivery["id"], DELIVERY);
+    assert_eq!(delivery["event"], "issues");
+    assert_eq!(delivery["action"], "opened");
+    assert_eq!(delivery["installation"], "2311213");
+    assert_eq!(delivery["repository"], "Codertocat/Hello-World");
+    assert_eq!(delivery["trace_id"], "72d3162ecc7811e381ab4c9367dc0958");
+}
+
+#[tokio::test]
+async fn api_requests_are_handled_in_a_span() {
+    let spans = Spans::default();
+    let _guard = tracing::subscriber::set_default(spans.clone());
+
+    let github = FakeGithub::start().unwrap();
+    github.client_pool().bot_login().await.unwrap();
+
+    let request = &spans.named("github_request")[0];
+    assert_eq!(request["method"], "GET");
+    assert_eq!(request["url"], format!("{}/app", github.url()));
+    assert_eq!(request["status"], "200");
+}
+
+#[tokio::test]
+async fn clients_send_the_trace_context() {
+    let github = FakeGithub::start().unwrap();
+    let pool = github.client_pool();
+    let context = TraceContext::from_delivery_id(DELIVERY).unwrap();
+
+    pool.get_with_trace_context(2311213, &context)
+        .repo("Codertocat", "Hello-World")
+        .git()
+        .delete_reference("heads/changes")
+        .compat()
+        .await
+        .unwrap();
+
+    let path = "/repos/Codertocat/Hello-World/git/refs/heads/changes";
+    let request = github
+        .requests()
+        .into_iter()
+        .find(|r| r.method == Method::DELETE && r.path == path)
+        .unwrap();
+
+    assert_eq!(request.headers["traceparent"], context.traceparent());
+}
@@ -0,0 +1,108 @@
+// Copyright (c) 2019 Jason White
+// Copyright (c) 2019 Mike Lubinets
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+#![cfg(feature = "fixtures")]
+
+use std::error::Error as StdError;
+
+use github_app::{
+    fixtures, verify_signature, Error, EventType, PayloadError, WebhookRequest,
+};
+
+/// A signed request for an opened issue.
+fn signed(secret: &str) -> WebhookRequest {
+    let fixture = fixtures::get(EventType::Issues, "opened").unwrap();
+    let request = fixture.request().secret(secret);
+    let body = request.body().to_vec();
+
+    WebhookRequest {
+        headers: request.into_request().headers().clone(),
+        body,
+    }
+}
+
+#[test]
+fn parses_requests_with_valid_signatures() {
+    let (delivery, _event) = signed("secret").parse(Some("secret")).unwrap();
+
+    assert_eq!(delivery.event, EventType::Issues);
+    assert_eq!(delivery.action.as_deref(), Some("opened"));
+}
+
+#[test]
+fn rejects_requests_with_bad_signatures() {
+    let err = signed("wrong").parse(Some("secret")).unwrap_err();
+
+    assert!(
+        matches!(err, Error::Payload(PayloadError::Mac(_))),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn rejects_requests_without_signatures() {
+    let mut request = signed("secret");
+    request.headers.remove("X-Hub-Signature");
+
+    let err = request.parse(Some("secret")).unwrap_err();
+    assert!(matches!(err, Error::MissingSignature), "{}", err);
+}
+
+#[test]
+fn skips_verification_without_secret() {
+    let (delivery, _event) = signed("wrong").parse(None).unwrap();
+
+    assert_eq!(delivery.event, EventType::Issues);
+}
+
+#[test]
+fn verifies_signature_headers() {
+    let request = signed("secret");
+    let header = request.headers["X-Hub-Signature"].to_str().unwrap();
+
+    verify_signature("secret", header, &request.body).unwrap();
+
+    let err = verify_signature("wrong", header, &request.body).unwrap_err();
+    assert!(
+        matches!(err, Error::Payload(PayloadError::Mac(_))),
+        "{}",
+        err
+    );
+
+    let err = verify_signature("secret", "md5=abc", &request.body).unwrap_err();
+    assert!(matches!(err, Error::InvalidSignature), "{}", err);
+}
+
+#[test]
+fn errors_have_sources() {
+    let mut request = signed("secret");
+    request.body = b"{".to_vec();
+
+    let err: Box<dyn StdError> = Box::new(request.parse(None).unwrap_err());
+
+    let payload = err.source().expect("error should have a source");
+    assert!(payload.is::<PayloadError>());
+
+    let json = payload
+        .source()
+        .expect("payload error should have a source");
+    assert!(json.is::<serde_json::Error>());
+}